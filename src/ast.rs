@@ -0,0 +1,93 @@
+//! The syntax tree of a source file, built by [`crate::parser`] and compiled to bytecode by
+//! [`crate::codegen`].
+//!
+//! By the time a tree exists every instruction is known and its operands match its
+//! signature, so tools working on the tree only have to deal with valid programs. Variable
+//! names are left as [`OperandKind::Name`]s, for [`crate::resolve`] to give addresses.
+
+use crate::isa::Form;
+use crate::span::Span;
+
+/// A whole source file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+}
+
+/// The statements between a `{` and its `}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    /// Covers both braces.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    /// `!name`, a jump target.
+    Label { name: String, span: Span },
+    Instr(Instr),
+    Decl(Decl),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Label { span, .. } => *span,
+            Stmt::Instr(instr) => instr.span,
+            Stmt::Decl(decl) => decl.span,
+        }
+    }
+}
+
+/// `var name @ $addr = value`, or the same with `let`: names a variable, and sets it if
+/// given a value. Either the address or the value may be left out, but not both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decl {
+    pub name: String,
+    /// Where the name is.
+    pub span: Span,
+    /// The address given after `@`, or `None` to have one allocated.
+    pub addr: Option<u8>,
+    /// The `var` or `let` setting the value, with the name as its target.
+    pub init: Option<Instr>,
+}
+
+/// An instruction and its operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instr {
+    /// The mnemonic, e.g. `pix`.
+    pub name: String,
+    /// Where the mnemonic is.
+    pub span: Span,
+    /// The form of the instruction the operands match.
+    pub form: &'static Form,
+    /// One for each operand type of the form, except that `+byte...` has as many as were
+    /// written.
+    pub operands: Vec<Operand>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperandKind {
+    /// A `$var` address.
+    Var(u8),
+    /// A `+byte`, or a number small enough to be used as one.
+    Byte(u8),
+    Num(i64),
+    Float(f64),
+    /// A string in the console's character set.
+    Str(Vec<u8>),
+    Array([u8; 8]),
+    /// An array with variable names in it, each element a `Byte` or a `Name`. Replaced by
+    /// an `Array` once the names have addresses.
+    NamedArray(Vec<Operand>),
+    /// The name of a label, routine or variable.
+    Name(String),
+    Block(Block),
+}
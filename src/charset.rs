@@ -0,0 +1,42 @@
+//! The character set of the ATC console.
+//!
+//! Strings are stored one byte per character. The console draws printable ASCII
+//! (`0x20` to `0x7e`) and understands `\0`, `\t` and `\n`; nothing else has a character,
+//! so other bytes can only be written with a `\xNN` escape.
+
+/// The byte the console uses for `chr`, if it has one.
+pub fn encode(chr: char) -> Option<u8> {
+    match chr {
+        '\0' | '\t' | '\n' | ' '..='~' => Some(chr as u8),
+        _ => None,
+    }
+}
+
+/// The character the console shows for `byte`, if it shows one.
+pub fn decode(byte: u8) -> Option<char> {
+    match byte {
+        0x00 | 0x09 | 0x0a | 0x20..=0x7e => Some(byte as char),
+        _ => None,
+    }
+}
+
+/// Spells `bytes` as the inside of a string literal, escaping whatever needs it.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    for &byte in bytes {
+        match decode(byte) {
+            Some('\0') => out.push_str("\\0"),
+            Some('\t') => out.push_str("\\t"),
+            Some('\n') => out.push_str("\\n"),
+            Some(chr @ ('"' | '\'' | '\\')) => {
+                out.push('\\');
+                out.push(chr);
+            }
+            Some(chr) => out.push(chr),
+            None => out.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,527 @@
+//! Compiles a [`Program`](crate::ast::Program) to ATC bytecode.
+//!
+//! A program is a header followed by code. The header is a run of flag bytes ended by a
+//! zero byte. Its flags are gathered into a [`Header`](crate::header::Header) by a `header { ... }` block or by
+//! the header directives, and written out when the header ends: at the end of the block,
+//! at an `endhead`, or at a `+00` in `headerbytes`. Header directives may only come before
+//! that and everything else only after it.
+//!
+//! Jumps go to a *code address*: the offset from the first byte after the header. Labels
+//! are defined at the code address of the next instruction, and `if` jumps to the code
+//! address just past its block. Each code address written into the bytecode is recorded
+//! as a [`Relocation`]. `bjmp` takes its address as written, so it is not one.
+
+use crate::Assembler;
+use crate::ast::{Decl, Instr, Operand, OperandKind, Stmt};
+use crate::charset;
+use crate::debug::{Entry, Frame, Location};
+use crate::diagnostics::{Diagnostic, did_you_mean};
+use crate::error::AscError;
+use crate::isa::{self, OP_VAR, OperandType, TAG_ARRAY, TAG_FLOAT, TAG_NUM, TAG_STR};
+use crate::lint::Warning;
+use crate::map::{Expansion, Variable};
+use crate::span::Span;
+
+use OperandKind::*;
+
+/// The most times a `rep` may repeat its block, and `rep`s inside it theirs all told.
+const MAX_REP: i64 = 0xffff;
+
+/// A code address written into the bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Offset of the 8-byte little-endian address in the bytecode.
+    pub at: usize,
+    /// The label it points to, or `None` for the end of an `if` block.
+    pub label: Option<String>,
+}
+
+/// A jump to a label not defined yet, whose address has to be patched in at `at`.
+#[derive(Debug, Clone)]
+pub(crate) struct Fixup {
+    at: usize,
+    name: String,
+    span: Span,
+}
+
+impl Assembler {
+    /// The code address of the next byte written. Only valid once the header has ended,
+    /// which [`Assembler::instr`] makes sure of before compiling any code.
+    fn here(&self) -> usize {
+        let Some(code_start) = self.code_start else {
+            unreachable!("code is only compiled after the header")
+        };
+
+        self.output.len() - code_start
+    }
+
+    /// Emits the address of the label `name`. A label that has not been defined yet gets
+    /// a placeholder, patched in by the next definition of the label.
+    fn jump_target(&mut self, name: &str, span: Span) {
+        self.relocations.push(Relocation { at: self.output.len(), label: Some(name.to_string()) });
+
+        if let Some(addr) = self.labels.get(name) {
+            self.output.extend(addr.to_le_bytes());
+            return
+        }
+
+        self.fixups.push(Fixup { at: self.output.len(), name: name.to_string(), span });
+        self.output.extend([0; 8]);
+    }
+
+    /// Defines the label `name` at `addr`, patching every jump to it that was waiting.
+    fn define_label(&mut self, name: &str, addr: usize) {
+        let output = &mut self.output;
+
+        self.fixups.retain(|fixup| {
+            if fixup.name != name {
+                return true
+            }
+
+            output[fixup.at..fixup.at + 8].copy_from_slice(&addr.to_le_bytes());
+            false
+        });
+
+        self.labels.insert(name.to_string(), addr);
+    }
+
+    /// The file and line of `span`, for the symbol map.
+    pub(crate) fn site(&self, span: Span) -> String {
+        format!("{}:{}", self.sources.name(span.file), span.line)
+    }
+
+    fn location(&self, span: Span) -> Location {
+        Location { file: self.sources.name(span.file).to_string(), line: span.line, col: span.col }
+    }
+
+    /// Records the bytes written since `start` as coming from `span`, for the debug info.
+    fn record_code(&mut self, start: usize, span: Span) {
+        if self.output.len() == start {
+            return
+        }
+
+        self.debug.push(Entry {
+            offset: start,
+            len: self.output.len() - start,
+            location: self.location(span),
+            expansion: self.frames.iter().rev().cloned().collect(),
+        });
+    }
+
+    /// Compiles `stmts` as expanded by `what`, written at `span`.
+    fn expand(&mut self, what: String, span: Span, stmts: &[Stmt]) {
+        let location = self.location(span);
+
+        self.frames.push(Frame { what, location });
+        self.compile(stmts);
+        self.frames.pop();
+    }
+
+    fn record_variable(&mut self, addr: u8, kind: &str, value: String, span: Span) {
+        let site = self.site(span);
+
+        self.variables.push(Variable { addr, kind: kind.to_string(), value, site });
+    }
+
+    /// Reports every jump to a label that was never defined.
+    pub(crate) fn unresolved_labels(&mut self) {
+        for Fixup { name, span, .. } in std::mem::take(&mut self.fixups) {
+            let suggestion = did_you_mean(&name, span, self.labels.keys().map(String::as_str));
+
+            self.report(Diagnostic::new(AscError::UnknownLabel { name }).at(span).suggest(suggestion))
+        }
+    }
+
+    /// Writes out the header, after which code begins.
+    fn end_header(&mut self) {
+        self.output.extend(self.header.bytes());
+        self.code_start = Some(self.output.len());
+    }
+
+    /// Adds a statement of a `header { ... }` block to the header.
+    fn header_directive(&mut self, stmt: &Stmt) -> Result<(), Diagnostic> {
+        let instr = match stmt {
+            Stmt::Label { name, span } => {
+                return Err(Diagnostic::new(AscError::LabelInHeader { name: name.clone() }).at(*span))
+            }
+            Stmt::Instr(instr) | Stmt::Decl(Decl { init: Some(instr), .. }) => instr,
+            // Only names a variable.
+            Stmt::Decl(Decl { init: None, .. }) => return Ok(()),
+        };
+
+        match instr.name.as_str() {
+            "keeploop" | "keepopen" => self.instr(instr),
+            "headerbytes" => {
+                // The block ends the header itself.
+                if let Some(zero) = instr.operands.iter().find(|operand| operand.kind == Byte(0)) {
+                    let error = AscError::OutOfRange { value: 0, target: "header byte", min: 1, max: 255 };
+                    return Err(Diagnostic::new(error).at(zero.span))
+                }
+
+                self.instr(instr)
+            }
+            _ => Err(Diagnostic::new(AscError::NotInHeader { inst: instr.name.clone() }).at(instr.span)),
+        }
+    }
+
+    /// Emits a `var`/`let` of `value` to `addr`: the opcode, a type tag, the value and the
+    /// target address. A string longer than one variable's 8 bytes is split over as many
+    /// consecutive variables as it needs, one instruction each. Returns the addresses set.
+    fn var_init(&mut self, instr: &Instr, addr: u8, value: &Operand) -> Result<Vec<u8>, Diagnostic> {
+        let Some(opcode) = instr.form.opcode else {
+            unreachable!("`{}` has an opcode", instr.name)
+        };
+
+        if let Str(str) = &value.kind {
+            let slots = str.len().div_ceil(8).max(1);
+
+            if addr as usize + slots > 0x100 {
+                return Err(Diagnostic::new(AscError::StringOverflow { addr, slots }).at(value.span))
+            }
+
+            let addrs: Vec<u8> = (0..slots).map(|slot| addr + slot as u8).collect();
+
+            for (&addr, chunk) in addrs.iter().zip(str.chunks(8).chain([&[][..]])) {
+                let mut bytes = [0; 8];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+
+                self.output.extend([opcode, TAG_STR]);
+                self.output.extend(bytes);
+                self.output.push(addr);
+
+                self.record_variable(addr, "str", format!("\"{}\"", charset::escape(chunk)), value.span);
+            }
+
+            self.usage.inits.push((addr, instr.span));
+
+            return Ok(addrs)
+        }
+
+        self.output.push(opcode);
+
+        match &value.kind {
+            Num(num) => {
+                self.output.push(TAG_NUM);
+                self.output.extend(num.to_le_bytes());
+                self.output.push(addr);
+
+                self.record_variable(addr, "num", num.to_string(), value.span);
+            }
+            Float(float) => {
+                self.output.push(TAG_FLOAT);
+                self.output.extend(float.to_le_bytes());
+                self.output.push(addr);
+
+                self.record_variable(addr, "float", format!("#{float}"), value.span);
+            }
+            Array(arr) => {
+                self.output.push(TAG_ARRAY);
+                self.output.extend(arr);
+                self.output.push(addr);
+
+                let bytes: Vec<String> = arr.iter().map(|byte| format!("+{byte:02x}")).collect();
+                self.record_variable(addr, "array", format!("[{}]", bytes.join(" ")), value.span);
+            }
+            _ => unreachable!("checked by the parser"),
+        }
+
+        self.usage.inits.push((addr, instr.span));
+
+        Ok(vec![addr])
+    }
+
+    /// Emits the result of constant folding: a `var` of `value`, tagged with `tag`, to `out`.
+    fn fold(&mut self, tag: u8, value: [u8; 8], out: u8) {
+        self.output.push(OP_VAR);
+        self.output.push(tag);
+        self.output.extend(value);
+        self.output.push(out);
+    }
+
+    /// Compiles `stmts`, reporting every statement that fails and carrying on after it.
+    pub(crate) fn compile(&mut self, stmts: &[Stmt]) {
+        // Set after an unconditional jump, until the next label makes code reachable again.
+        let mut after_jump = false;
+
+        for stmt in stmts {
+            match stmt {
+                Stmt::Label { .. } => after_jump = false,
+                Stmt::Instr(instr) if instr.name == "routine" => {}
+                Stmt::Decl(Decl { init: None, .. }) => {}
+                _ if after_jump => {
+                    self.warn(Warning::UnreachableCode, stmt.span());
+                    after_jump = false;
+                }
+                _ => {}
+            }
+
+            let jumps = matches!(stmt, Stmt::Instr(instr) if instr.name == "jmp" || instr.name == "bjmp");
+
+            let start = self.output.len();
+
+            match self.statement(stmt) {
+                Ok(()) => after_jump |= jumps,
+                Err(diagnostic) => self.report(diagnostic),
+            }
+
+            // Instructions with blocks record their own code, apart from their blocks'.
+            if let Stmt::Instr(instr) | Stmt::Decl(Decl { init: Some(instr), .. }) = stmt
+                && !matches!(instr.name.as_str(), "if" | "call" | "rep")
+            {
+                self.record_code(start, instr.span);
+            }
+        }
+    }
+
+    /// Reports `warning` at the level its lint is set to.
+    pub(crate) fn warn(&mut self, warning: Warning, span: Span) {
+        let suggestion = warning.suggestion(span);
+
+        if let Some(diagnostic) = Diagnostic::lint(warning, &self.lints) {
+            self.report(diagnostic.at(span).suggest(suggestion))
+        }
+    }
+
+    pub(crate) fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.report(diagnostic)
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), Diagnostic> {
+        match stmt {
+            Stmt::Instr(instr) | Stmt::Decl(Decl { init: Some(instr), .. }) => self.instr(instr),
+            Stmt::Decl(Decl { init: None, .. }) => Ok(()),
+            Stmt::Label { name, span } => {
+                if self.code_start.is_none() {
+                    return Err(Diagnostic::new(AscError::LabelInHeader { name: name.clone() }).at(*span))
+                }
+                if self.labels.contains_key(name) {
+                    return Err(Diagnostic::new(AscError::DuplicateLabel { name: name.clone() }).at(*span))
+                }
+                self.usage.label_spans.push((name.clone(), *span));
+                self.define_label(name, self.here());
+                Ok(())
+            }
+        }
+    }
+
+    fn instr(&mut self, instr: &Instr) -> Result<(), Diagnostic> {
+        let header = isa::lookup(&instr.name).is_some_and(|instruction| instruction.header);
+
+        // Routines, calls and repeats write no code of their own, only their blocks do.
+        match (header, self.code_start) {
+            (true, Some(_)) => {
+                return Err(Diagnostic::new(AscError::HeaderEnded { inst: instr.name.clone() }).at(instr.span))
+            }
+            (false, None) if !matches!(instr.name.as_str(), "routine" | "call" | "rep") => {
+                return Err(Diagnostic::new(AscError::CodeInHeader { inst: instr.name.clone() }).at(instr.span))
+            }
+            _ => {}
+        }
+
+        let operands: Vec<&OperandKind> = instr.operands.iter().map(|operand| &operand.kind).collect();
+
+        match (instr.name.as_str(), &operands[..]) {
+            ("header", [Block(block)]) => {
+                for stmt in &block.stmts {
+                    if let Err(diagnostic) = self.header_directive(stmt) {
+                        self.report(diagnostic)
+                    }
+                }
+
+                self.end_header();
+            }
+            ("keeploop" | "keepopen", _) => {
+                let flag = match instr.name.as_str() {
+                    "keeploop" => &mut self.header.keep_loop,
+                    _ => &mut self.header.keep_open,
+                };
+
+                if std::mem::replace(flag, true) {
+                    return Err(Diagnostic::new(AscError::DuplicateHeaderFlag { inst: instr.name.clone() }).at(instr.span))
+                }
+            }
+            ("endhead", _) => self.end_header(),
+            ("headerbytes", _) => {
+                for operand in &instr.operands {
+                    let Byte(byte) = operand.kind else { unreachable!("checked by the parser") };
+
+                    if self.code_start.is_some() {
+                        return Err(Diagnostic::new(AscError::HeaderEnded { inst: instr.name.clone() }).at(operand.span))
+                    }
+
+                    // The bytes of the flags set them, so the header stays in canonical order.
+                    let (flag, name) = match byte {
+                        0 => {
+                            self.end_header();
+                            continue
+                        }
+                        isa::FLAG_KEEP_LOOP => (&mut self.header.keep_loop, "keeploop"),
+                        isa::FLAG_KEEP_OPEN => (&mut self.header.keep_open, "keepopen"),
+                        _ => {
+                            self.header.extra.push(byte);
+                            continue
+                        }
+                    };
+
+                    if std::mem::replace(flag, true) {
+                        return Err(Diagnostic::new(AscError::DuplicateHeaderFlag { inst: name.to_string() }).at(operand.span))
+                    }
+                }
+            }
+            ("var", [Var(addr), _]) => {
+                let addrs = self.var_init(instr, *addr, &instr.operands[1])?;
+
+                self.usage.assigned.extend(addrs);
+            }
+            ("let", [Var(addr), _]) => {
+                let addrs = self.var_init(instr, *addr, &instr.operands[1])?;
+
+                if let Some(&addr) = addrs.iter().find(|addr| self.usage.assigned.contains(addr)) {
+                    self.warn(Warning::RedundantLet { addr }, instr.span);
+                }
+            }
+            (name @ ("fdiv" | "fsub" | "fadd" | "fmul"), [Float(lhs), Float(rhs), Var(out)]) => {
+                let value = match name {
+                    "fdiv" => lhs / rhs,
+                    "fsub" => lhs - rhs,
+                    "fadd" => lhs + rhs,
+                    _ => lhs * rhs,
+                };
+
+                if !value.is_finite() {
+                    let error = AscError::ConstantOverflow { inst: instr.name.clone(), lhs: format!("#{lhs:?}"), rhs: format!("#{rhs:?}") };
+                    return Err(Diagnostic::new(error).at(instr.span))
+                }
+
+                self.fold(TAG_FLOAT, value.to_le_bytes(), *out)
+            }
+            (name @ ("div" | "sub" | "add" | "mul"), [Num(lhs), Num(rhs), Var(out)]) => {
+                let value = match name {
+                    "div" => lhs.checked_div(*rhs),
+                    "sub" => lhs.checked_sub(*rhs),
+                    "add" => lhs.checked_add(*rhs),
+                    _ => lhs.checked_mul(*rhs),
+                };
+
+                let Some(value) = value else {
+                    let error = AscError::ConstantOverflow { inst: instr.name.clone(), lhs: lhs.to_string(), rhs: rhs.to_string() };
+                    return Err(Diagnostic::new(error).at(instr.span))
+                };
+
+                self.fold(TAG_NUM, value.to_le_bytes(), *out)
+            }
+            ("routine", [Name(routine), Block(block)]) => {
+                self.usage.routine_spans.push((routine.clone(), instr.operands[0].span));
+
+                self.routines.insert(routine.clone(), block.clone());
+            }
+            ("call", [Name(routine)]) => {
+                let span = instr.operands[0].span;
+
+                self.usage.called.insert(routine.clone());
+
+                let Some(block) = self.routines.get(routine).cloned() else {
+                    let suggestion = did_you_mean(routine, span, self.routines.keys().map(String::as_str));
+
+                    return Err(Diagnostic::new(AscError::UnknownRoutine { name: routine.clone() }).at(span).suggest(suggestion))
+                };
+
+                if self.expanding.contains(routine) {
+                    return Err(Diagnostic::new(AscError::RecursiveRoutine { name: routine.clone() }).at(span))
+                }
+
+                let start = self.output.len();
+
+                self.expanding.push(routine.clone());
+                self.expand(format!("call {routine}"), instr.span, &block.stmts);
+                self.expanding.pop();
+
+                let site = self.site(span);
+                self.expansions.push(Expansion { routine: routine.clone(), start, end: self.output.len(), site });
+            }
+            ("if", [Var(addr), Block(block)]) => {
+                let start = self.output.len();
+
+                self.usage.read(*addr);
+
+                self.output.extend(instr.form.opcode);
+                self.output.push(*addr);
+
+                let at = self.output.len();
+
+                self.relocations.push(Relocation { at, label: None });
+                self.output.extend([0; 8]);
+
+                self.record_code(start, instr.span);
+
+                self.compile(&block.stmts);
+
+                let end = self.here().to_le_bytes();
+
+                self.output[at..at + 8].copy_from_slice(&end);
+            }
+            ("rep", [Num(count), Block(block)]) => {
+                if !(0..=MAX_REP).contains(count) {
+                    let error = AscError::OutOfRange { value: *count, target: "rep count", min: 0, max: MAX_REP };
+                    return Err(Diagnostic::new(error).at(instr.operands[0].span))
+                }
+
+                // Each count is at most `MAX_REP`, so this cannot overflow.
+                let total = self.repeats.iter().product::<i64>() * count;
+
+                if total > MAX_REP {
+                    let error = AscError::NestedRepeats { total, max: MAX_REP };
+                    return Err(Diagnostic::new(error).at(instr.operands[0].span))
+                }
+
+                self.repeats.push(*count);
+
+                for i in 1..=*count {
+                    self.expand(format!("rep {i} of {count}"), instr.span, &block.stmts);
+                }
+
+                self.repeats.pop();
+            }
+            _ => self.emit(instr),
+        }
+
+        Ok(())
+    }
+
+    /// Emits an instruction that compiles to its opcode followed by its operands, as
+    /// described by the [instruction table](crate::isa).
+    fn emit(&mut self, instr: &Instr) {
+        let Some(opcode) = instr.form.opcode else {
+            unreachable!("`{}` has no opcode of its own", instr.name)
+        };
+
+        self.output.push(opcode);
+
+        for (operand, ty) in instr.operands.iter().zip(instr.form.operands) {
+            match &operand.kind {
+                Var(addr) => {
+                    if *ty == OperandType::Var {
+                        self.usage.read(*addr);
+                    }
+                    self.output.push(*addr)
+                }
+                Byte(byte) => self.output.push(*byte),
+                Num(num) => self.output.extend(num.to_le_bytes()),
+                Float(float) => self.output.extend(float.to_le_bytes()),
+                Array(arr) => {
+                    for addr in arr {
+                        self.usage.read(*addr);
+                    }
+                    self.output.extend(arr)
+                }
+                Name(label) => {
+                    self.usage.jumped.insert(label.clone());
+
+                    self.jump_target(label, operand.span)
+                }
+                Str(_) | Block(_) => unreachable!("`{}` has no plain encoding", instr.name),
+                NamedArray(_) => unreachable!("names are resolved before compiling"),
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Named constants, defined by `const NAME = value` or `NAME equ value` and usable
+//! everywhere after that.
+//!
+//! A constant's value is a number, `+byte` or `#float`, so a constant can be used anywhere
+//! one of those can, including in arrays and as a `rep` count. Every use is left as a
+//! [`TokenKind::Constant`] holding the value, which the parser reads as the value only
+//! where a value can go, so a label or routine may share a constant's name. A variable
+//! may not, as a variable name can go where a value can too. Each use keeps its own span,
+//! so errors about a value point at where it is used.
+
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::AscError;
+use crate::isa;
+use crate::span::Span;
+use crate::tokens::{Token, TokenKind};
+
+/// What the value of a constant can be.
+const VALUE: &str = "num | +byte | #float";
+
+/// Takes the constant definitions out of `tokens`, turning every use of a constant into a
+/// [`TokenKind::Constant`] holding its value.
+pub fn substitute(tokens: Vec<Token>, diagnostics: &mut Diagnostics) -> Vec<Token> {
+    Constants { tokens: tokens.into_iter().peekable(), values: HashMap::new(), variables: HashSet::new(), diagnostics }
+        .substitute()
+}
+
+struct Constants<'a> {
+    tokens: Peekable<IntoIter<Token>>,
+    values: HashMap<String, TokenKind>,
+    /// The variable names declared so far.
+    variables: HashSet<String>,
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl Constants<'_> {
+    fn substitute(&mut self) -> Vec<Token> {
+        let mut out = vec![];
+
+        while let Some(token) = self.tokens.next() {
+            let defined = match &token.kind {
+                TokenKind::Ident(keyword) if keyword == "const" => self.name(token.span).and_then(|(name, span)| {
+                    self.expect_assign(span)?;
+                    self.define(name, span, "const", token.span)
+                }),
+                TokenKind::Ident(name) if let Some(equ) = self.tokens.next_if(is_equ) => {
+                    self.define(name.clone(), token.span, "equ", equ.span)
+                }
+                TokenKind::Ident(keyword) if matches!(keyword.as_str(), "var" | "let") => {
+                    out.push(token);
+                    self.declaration(&mut out);
+                    continue
+                }
+                TokenKind::Ident(_) => {
+                    out.push(self.constant(token));
+                    continue
+                }
+                _ => {
+                    out.push(token);
+                    continue
+                }
+            };
+
+            if let Err(diagnostic) = defined {
+                self.diagnostics.report(diagnostic);
+            }
+        }
+
+        out
+    }
+
+    /// `token` as a use of a constant, if it names one.
+    fn constant(&self, token: Token) -> Token {
+        match &token.kind {
+            TokenKind::Ident(name) if let Some(value) = self.values.get(name) => {
+                let kind = TokenKind::Constant { name: name.clone(), value: Box::new(value.clone()) };
+                Token::new(kind, token.span)
+            }
+            _ => token,
+        }
+    }
+
+    /// Reads the name after a `var` or `let`. The name of a declaration, `var name @` or
+    /// `var name =`, is left as a name even if it is a constant's, so it still parses.
+    fn declaration(&mut self, out: &mut Vec<Token>) {
+        let Some(token) = self.tokens.next_if(|token| matches!(token.kind, TokenKind::Ident(_))) else { return };
+
+        let declares = self.tokens.peek().is_some_and(|next| matches!(next.kind, TokenKind::At | TokenKind::Assign));
+
+        let TokenKind::Ident(name) = &token.kind else { unreachable!("just checked") };
+
+        if !declares {
+            return out.push(self.constant(token))
+        }
+
+        if self.values.contains_key(name) {
+            let error = AscError::ConstantVariable { name: name.clone() };
+            self.diagnostics.report(Diagnostic::new(error).at(token.span));
+        }
+
+        self.variables.insert(name.clone());
+        out.push(token);
+    }
+
+    /// Reads the name after the `const` at `span`.
+    fn name(&mut self, span: Span) -> Result<(String, Span), Diagnostic> {
+        match self.tokens.next_if(|token| matches!(token.kind, TokenKind::Ident(_))) {
+            Some(Token { kind: TokenKind::Ident(name), span }) => Ok((name, span)),
+            _ => Err(self.missing("const", span)),
+        }
+    }
+
+    /// Reads the `=` after the name at `span`.
+    fn expect_assign(&mut self, span: Span) -> Result<(), Diagnostic> {
+        match self.tokens.next_if(|token| token.kind == TokenKind::Assign) {
+            Some(_) => Ok(()),
+            None => Err(self.missing("const", span)),
+        }
+    }
+
+    /// Reads the value of the constant `name`, defined by the `keyword` at `span`.
+    fn define(&mut self, name: String, name_span: Span, keyword: &str, span: Span) -> Result<(), Diagnostic> {
+        // Left for the parser, like a missing operand.
+        let Some(token) = self.tokens.next_if(|token| match &token.kind {
+            TokenKind::EndL => false,
+            TokenKind::Ident(ident) => isa::lookup(ident).is_none(),
+            _ => true,
+        }) else {
+            return Err(self.missing(keyword, span))
+        };
+
+        let value = match token.kind {
+            TokenKind::Num(_) | TokenKind::Byte(_) | TokenKind::Float(_) => token.kind,
+            TokenKind::Ident(ref constant) if let Some(value) = self.values.get(constant) => value.clone(),
+            // Already reported when it failed to tokenize.
+            TokenKind::Error => return Ok(()),
+            found => {
+                let error = AscError::InvalidOperand { inst: keyword.to_string(), expected: signature(keyword), found };
+                return Err(Diagnostic::new(error).at(token.span))
+            }
+        };
+
+        if isa::lookup(&name).is_some() {
+            return Err(Diagnostic::new(AscError::ReservedName { name }).at(name_span))
+        }
+
+        if self.values.contains_key(&name) {
+            return Err(Diagnostic::new(AscError::DuplicateConstant { name }).at(name_span))
+        }
+
+        if self.variables.contains(&name) {
+            return Err(Diagnostic::new(AscError::ConstantVariable { name }).at(name_span))
+        }
+
+        self.values.insert(name, value);
+
+        Ok(())
+    }
+
+    fn missing(&self, keyword: &str, span: Span) -> Diagnostic {
+        let error = AscError::MissingOperand { inst: keyword.to_string(), expected: signature(keyword) };
+
+        Diagnostic::new(error).at(span)
+    }
+}
+
+fn is_equ(token: &Token) -> bool {
+    matches!(&token.kind, TokenKind::Ident(ident) if ident == "equ")
+}
+
+/// How a constant is defined with `keyword`, for error messages.
+fn signature(keyword: &str) -> String {
+    match keyword {
+        "const" => format!("name = {VALUE}"),
+        _ => VALUE.to_string(),
+    }
+}
@@ -0,0 +1,73 @@
+//! Debug info: which source each byte of an assembled program came from, written next to
+//! the bytecode as an `.atc.dbg` file by `-g`.
+//!
+//! There is one line for every instruction, giving its file offset and length in hex, where
+//! it is written, and, for code inlined by a `call` or unrolled by a `rep`, the chain of
+//! expansions that put it there, innermost first:
+//!
+//! ```text
+//! 000d  03  lib.asc:2:18  <- call clear (game.asc:5:1)  <- rep 2 of 3 (game.asc:4:1)
+//! ```
+
+use std::fmt;
+
+/// A position in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+/// One expansion a piece of code was written by, e.g. `call clear`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// `call <routine>`, or `rep <n> of <count>` for the nth time round a `rep`.
+    pub what: String,
+    pub location: Location,
+}
+
+/// The bytes one instruction compiled to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub offset: usize,
+    pub len: usize,
+    pub location: Location,
+    /// The expansions the instruction was written by, innermost first.
+    pub expansion: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DebugInfo {
+    /// In offset order.
+    pub entries: Vec<Entry>,
+}
+
+impl DebugInfo {
+    /// The instruction the byte at `offset` belongs to.
+    pub fn at(&self, offset: usize) -> Option<&Entry> {
+        self.entries.iter().find(|entry| (entry.offset..entry.offset + entry.len).contains(&offset))
+    }
+}
+
+impl fmt::Display for DebugInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for Entry { offset, len, location, expansion } in &self.entries {
+            write!(f, "{offset:04x}  {len:02x}  {location}")?;
+
+            for Frame { what, location } in expansion {
+                write!(f, "  <- {what} ({location})")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
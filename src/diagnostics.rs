@@ -0,0 +1,295 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::error::AscError;
+use crate::lint::{Level, LintLevels, Warning};
+use crate::span::{SourceMap, Span};
+use crate::tokens::TokenKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    Error(AscError),
+    /// A lint, with the name its level was set by. Its severity is an error if the lint
+    /// was denied.
+    Lint(Warning, &'static str),
+}
+
+/// A machine-applicable fix: replace the text at `span` with `replacement`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A single problem found while assembling a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Boxed so results carrying a diagnostic stay small on the happy path.
+    pub kind: Box<DiagnosticKind>,
+    /// Where in the source the problem was found, if known.
+    pub span: Option<Span>,
+    pub suggestion: Option<Box<Suggestion>>,
+}
+
+impl Diagnostic {
+    pub fn new(error: AscError) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, kind: Box::new(DiagnosticKind::Error(error)), span: None, suggestion: None }
+    }
+
+    /// A diagnostic for `warning` at the level `lints` sets, or `None` if the lint is allowed.
+    pub fn lint(warning: Warning, lints: &LintLevels) -> Option<Diagnostic> {
+        let lint = warning.lint();
+
+        let severity = match lints.level(lint) {
+            Level::Allow => return None,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error,
+        };
+
+        Some(Diagnostic { severity, kind: Box::new(DiagnosticKind::Lint(warning, lints.set_by(lint))), span: None, suggestion: None })
+    }
+
+    pub fn at(mut self, span: Span) -> Diagnostic {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn suggest(mut self, suggestion: Option<Suggestion>) -> Diagnostic {
+        self.suggestion = suggestion.map(Box::new);
+        self
+    }
+
+    /// Renders the diagnostic with a snippet of the offending source, see [`crate::render`].
+    pub fn render(&self, sources: &SourceMap, color: bool) -> String {
+        crate::render::render(self, sources, color)
+    }
+
+    /// The diagnostic as a single line of JSON, see [`crate::json`].
+    pub fn to_json(&self, sources: &SourceMap) -> String {
+        crate::json::to_json(self, sources)
+    }
+
+    /// The stable code of the error or lint, see [`AscError::code`] and [`crate::lint::Lint::code`].
+    pub fn code(&self) -> &'static str {
+        match &*self.kind {
+            DiagnosticKind::Error(error) => error.code(),
+            DiagnosticKind::Lint(warning, _) => warning.lint().code(),
+        }
+    }
+
+    /// The error, for diagnostics that are not lints.
+    pub fn error(&self) -> Option<&AscError> {
+        match &*self.kind {
+            DiagnosticKind::Error(error) => Some(error),
+            DiagnosticKind::Lint(..) => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match &*self.kind {
+            DiagnosticKind::Error(error) => error.to_string(),
+            DiagnosticKind::Lint(warning, _) => warning.to_string(),
+        }
+    }
+
+    pub fn help(&self) -> Option<String> {
+        match &*self.kind {
+            DiagnosticKind::Error(error) => error.help(),
+            DiagnosticKind::Lint(warning, _) => warning.help(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Suggests replacing the misspelt `name` at `span` with the most similar of `candidates`,
+/// if any is close enough to be a plausible typo.
+pub(crate) fn did_you_mean<'a>(name: &str, span: Span, candidates: impl IntoIterator<Item = &'a str>) -> Option<Suggestion> {
+    let max = (name.chars().count() / 3).max(1);
+
+    let (distance, best) = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min()?;
+
+    (distance != 0).then(|| Suggestion {
+        message: format!("did you mean `{best}`?"),
+        span,
+        replacement: best.to_string(),
+    })
+}
+
+/// Edit distance between `a` and `b`, in characters, counting a swap of two neighbouring
+/// characters as a single edit (so `pxi` is one edit away from `pix`).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            let mut distance = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(table[i - 2][j - 2] + 1);
+            }
+
+            table[i][j] = distance;
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
+impl From<AscError> for Diagnostic {
+    fn from(error: AscError) -> Diagnostic {
+        Diagnostic::new(error)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code(), self.message())?;
+
+        if let Some(span) = self.span {
+            write!(f, " at {}:{}", span.line, span.col)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for Diagnostic {}
+
+/// Every diagnostic produced by one call to [`crate::Assembler::assemble`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diagnostics {
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.list.push(diagnostic)
+    }
+
+    /// Pushes `diagnostic`, unless it is a knock-on effect of an error token or the same
+    /// diagnostic was already pushed (e.g. by an earlier `rep` iteration or routine call).
+    pub(crate) fn report(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.error().and_then(AscError::found) == Some(&TokenKind::Error) {
+            return
+        }
+
+        if self.list.contains(&diagnostic) {
+            return
+        }
+
+        self.list.push(diagnostic)
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.list.iter()
+    }
+
+    /// Whether any diagnostic is an error (including denied lints).
+    pub fn has_errors(&self) -> bool {
+        self.list.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.list.iter().filter(|diagnostic| diagnostic.is_error()).count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.len() - self.error_count()
+    }
+
+    /// Orders the diagnostics by where they occur in the source, those without a span last.
+    pub fn sort(&mut self) {
+        self.list.sort_by_key(|diagnostic| match diagnostic.span {
+            Some(span) => (false, span.file.0, span.start),
+            None => (true, 0, 0),
+        })
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Diagnostics {
+        Diagnostics { list: vec![diagnostic] }
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.list.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Diagnostics {}
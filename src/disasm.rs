@@ -0,0 +1,242 @@
+//! Turns ATC bytecode back into source, using the [instruction table](crate::isa).
+//!
+//! Bytecode written by this assembler disassembles to source that assembles back to the
+//! same bytes. Where several instructions share an encoding the first in the table is
+//! used: `fjmp` for `if`, `jmp` for `bjmp` and `noop` for a zero byte in code. Routines,
+//! `rep`s and folded constants show up as the code they compiled to.
+//!
+//! The header is written as a `header { ... }` block, or as a bare `endhead` if it is empty.
+//!
+//! Every jump target that starts an instruction gets a label named after its code address,
+//! like `!l_001f`. A `jmp` anywhere else becomes a `bjmp`. Bytes that no instruction
+//! encodes to, such as a conditional jump into the middle of an instruction, are listed in
+//! comments and cannot be assembled back.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::charset;
+use crate::header::Header;
+use crate::isa::{self, Form, OperandType, TAG_ARRAY, TAG_FLOAT, TAG_NUM, TAG_STR};
+
+enum Operand {
+    Text(String),
+    /// The code address of a label to jump to.
+    Target(usize),
+}
+
+enum Decoded {
+    Instr { mnemonic: &'static str, operands: Vec<Operand> },
+    /// Bytes that do not decode to anything.
+    Unknown,
+}
+
+/// Disassembles `bytes` into source, with the code address of each instruction in a
+/// comment after it.
+pub fn disassemble(bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    let code_start = header(bytes, &mut out);
+    let code = &bytes[code_start..];
+
+    // The code address, length and decoding of every instruction.
+    let mut lines: Vec<(usize, usize, Decoded)> = vec![];
+    let mut at = 0;
+
+    while at < code.len() {
+        match decode(&code[at..]) {
+            Some((len, decoded)) => {
+                lines.push((at, len, decoded));
+                at += len;
+            }
+            None => {
+                if let Some((_, len, Decoded::Unknown)) = lines.last_mut() {
+                    *len += 1;
+                } else {
+                    lines.push((at, 1, Decoded::Unknown));
+                }
+                at += 1;
+            }
+        }
+    }
+
+    // Jump targets that can be labelled: the start of an instruction, or the end of the code.
+    let mut starts: BTreeSet<usize> = lines.iter()
+        .filter(|(_, _, decoded)| matches!(decoded, Decoded::Instr { .. }))
+        .map(|&(at, _, _)| at)
+        .collect();
+    starts.insert(code.len());
+
+    let targets: BTreeSet<usize> = lines.iter()
+        .flat_map(|(_, _, decoded)| match decoded {
+            Decoded::Instr { operands, .. } => operands.iter().collect(),
+            Decoded::Unknown => vec![],
+        })
+        .filter_map(|operand| match operand {
+            Operand::Target(target) if starts.contains(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+
+    for (at, len, decoded) in lines {
+        if targets.contains(&at) {
+            let _ = writeln!(out, "!{}", label(at));
+        }
+
+        let line = match decoded {
+            Decoded::Instr { mnemonic, operands } => instr(mnemonic, operands, &targets),
+            Decoded::Unknown => None,
+        };
+
+        let _ = match line {
+            Some(line) => writeln!(out, "    {line:<48}// {at:04x}"),
+            None => {
+                let hex: Vec<String> = code[at..at + len].iter().map(|byte| format!("{byte:02x}")).collect();
+                writeln!(out, "    // {at:04x}: cannot disassemble {}", hex.join(" "))
+            }
+        };
+    }
+
+    if targets.contains(&code.len()) {
+        let _ = writeln!(out, "!{}", label(code.len()));
+    }
+
+    out
+}
+
+fn label(addr: usize) -> String {
+    format!("l_{addr:04x}")
+}
+
+/// Writes out the header of `bytes`, returning the offset of the code after it.
+fn header(bytes: &[u8], out: &mut String) -> usize {
+    let end = bytes.iter().position(|&byte| byte == 0);
+    let header = Header::decode(&bytes[..end.unwrap_or(bytes.len())]);
+
+    let mut directives = vec![];
+    if header.keep_loop {
+        directives.push("keeploop".to_string());
+    }
+    if header.keep_open {
+        directives.push("keepopen".to_string());
+    }
+    if !header.extra.is_empty() {
+        let bytes: Vec<String> = header.extra.iter().map(|byte| format!("+{byte:02x}")).collect();
+        directives.push(format!("headerbytes {}", bytes.join(" ")));
+    }
+
+    match end {
+        Some(end) => {
+            let _ = match directives.is_empty() {
+                true => writeln!(out, "endhead\n"),
+                false => writeln!(out, "header {{ {} }}\n", directives.join("; ")),
+            };
+            end + 1
+        }
+        // A header that never ends, so there is no code either.
+        None => {
+            for directive in directives {
+                let _ = writeln!(out, "{directive}");
+            }
+            bytes.len()
+        }
+    }
+}
+
+/// The first code instruction in the table whose form compiles to `opcode`.
+fn lookup(opcode: u8) -> Option<(&'static str, &'static Form)> {
+    isa::INSTRUCTIONS.iter()
+        .filter(|instruction| !instruction.header)
+        .find_map(|instruction| {
+            let form = instruction.forms.iter().find(|form| form.opcode == Some(opcode))?;
+            Some((instruction.mnemonic, form))
+        })
+}
+
+/// Decodes the instruction at the start of `code`, returning its length.
+fn decode(code: &[u8]) -> Option<(usize, Decoded)> {
+    let (mnemonic, form) = lookup(*code.first()?)?;
+
+    let mut at = 1;
+    let mut take = |len: usize| {
+        let bytes = code.get(at..at + len)?;
+        at += len;
+        Some(bytes)
+    };
+
+    let mut operands = vec![];
+
+    // `var` and `let` write the value before the target address.
+    if form.operands == [OperandType::Out, OperandType::Value] {
+        let value = value(take(9)?)?;
+        operands.push(Operand::Text(format!("${:02x}", take(1)?[0])));
+        operands.push(Operand::Text(value));
+
+        return Some((at, Decoded::Instr { mnemonic, operands }))
+    }
+
+    for ty in form.operands {
+        let bytes = take(ty.width()?)?;
+
+        operands.push(match ty {
+            OperandType::Var | OperandType::Out => Operand::Text(format!("${:02x}", bytes[0])),
+            OperandType::Byte => Operand::Text(format!("+{:02x}", bytes[0])),
+            OperandType::Num => Operand::Text(i64::from_le_bytes(bytes.try_into().ok()?).to_string()),
+            OperandType::Label => Operand::Target(u64::from_le_bytes(bytes.try_into().ok()?) as usize),
+            OperandType::Array => {
+                let addrs: Vec<String> = bytes.iter().map(|addr| format!("${addr:02x}")).collect();
+                Operand::Text(format!("[{}]", addrs.join(" ")))
+            }
+            _ => return None,
+        });
+    }
+
+    Some((at, Decoded::Instr { mnemonic, operands }))
+}
+
+/// A `var`/`let` value: a type tag and 8 bytes.
+fn value(bytes: &[u8]) -> Option<String> {
+    let (&tag, bytes) = bytes.split_first()?;
+    let array: [u8; 8] = bytes.try_into().ok()?;
+
+    match tag {
+        TAG_STR => {
+            // Strings are padded to 8 bytes with zeros, which the assembler puts back.
+            let len = array.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+            Some(format!("\"{}\"", charset::escape(&array[..len])))
+        }
+        TAG_NUM => Some(i64::from_le_bytes(array).to_string()),
+        TAG_FLOAT => {
+            let float = f64::from_le_bytes(array);
+            // `{:?}` prints the shortest text that parses back to the same float.
+            float.is_finite().then(|| format!("#{float:?}"))
+        }
+        TAG_ARRAY => {
+            let bytes: Vec<String> = array.iter().map(|byte| format!("+{byte:02x}")).collect();
+            Some(format!("[{}]", bytes.join(" ")))
+        }
+        _ => None,
+    }
+}
+
+/// Spells out an instruction, unless it jumps somewhere no label can be.
+fn instr(mnemonic: &str, operands: Vec<Operand>, targets: &BTreeSet<usize>) -> Option<String> {
+    let mut mnemonic = mnemonic;
+    let mut text = vec![];
+
+    for operand in operands {
+        text.push(match operand {
+            Operand::Text(text) => text,
+            Operand::Target(target) if targets.contains(&target) => label(target),
+            Operand::Target(target) if mnemonic == "jmp" => {
+                mnemonic = "bjmp";
+                (target as i64).to_string()
+            }
+            Operand::Target(_) => return None,
+        });
+    }
+
+    text.insert(0, mnemonic.to_string());
+
+    Some(text.join(" "))
+}
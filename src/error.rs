@@ -0,0 +1,286 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::tokens::TokenKind;
+
+/// Every way assembling or linking a file can fail.
+///
+/// Each variant has a stable code (see [`AscError::code`]) so tools can match on the kind
+/// of failure without parsing the message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AscError {
+    /// A character that cannot start any token.
+    UnexpectedChar { chr: char },
+    /// A literal whose digits could not be parsed; `kind` names the literal form.
+    InvalidLiteral { kind: &'static str, text: String },
+    /// A `$` variable address above `$ff`.
+    VarOutOfBounds { value: i64 },
+    /// A string literal that needs `slots` variables from `addr`, running past `$ff`.
+    StringOverflow { addr: u8, slots: usize },
+    /// The file ended in the middle of `context`.
+    UnexpectedEof { context: &'static str },
+    /// An array literal with a length other than 8.
+    ArrayLength { len: usize },
+    /// An array element that is neither a byte nor a variable address.
+    InvalidArrayElement { found: TokenKind },
+    /// A block or `/` comment still open at the end of the file.
+    UnterminatedComment { line: usize, close: &'static str },
+    /// A character in a string or character literal the console cannot show.
+    UnmappableChar { chr: char },
+    /// A `\` followed by something that is not a known escape.
+    InvalidEscape { text: String },
+    /// A `}` or `]` with nothing open for it to close.
+    UnmatchedDelimiter { found: TokenKind },
+    /// An identifier in instruction position that is not a known mnemonic.
+    UnknownInstruction { name: String },
+    /// A token in instruction position that is not an identifier, label or `;`.
+    UnexpectedToken { found: TokenKind },
+    /// An operand of `inst` that does not match its signature.
+    InvalidOperand { inst: String, expected: String, found: TokenKind },
+    /// `inst` ran out of operands before its signature was satisfied.
+    MissingOperand { inst: String, expected: String },
+    /// A jump to a label that has not been defined.
+    UnknownLabel { name: String },
+    /// A `call` to a routine that has not been declared.
+    UnknownRoutine { name: String },
+    /// A routine that ends up calling itself while being inlined.
+    RecursiveRoutine { name: String },
+    /// Constant folding of `inst` overflowed or divided by zero, with the operands as written.
+    ConstantOverflow { inst: String, lhs: String, rhs: String },
+    /// A label placed before the end of the header.
+    LabelInHeader { name: String },
+    /// A number too big or small for the `target` it is used as, e.g. a byte.
+    OutOfRange { value: i64, target: &'static str, min: i64, max: i64 },
+    /// An instruction placed before the end of the header.
+    CodeInHeader { inst: String },
+    /// A header directive placed after the end of the header.
+    HeaderEnded { inst: String },
+    /// A header flag set a second time.
+    DuplicateHeaderFlag { inst: String },
+    /// Something other than a header flag or `headerbytes` inside a `header { ... }` block.
+    NotInHeader { inst: String },
+    /// A variable name that is not declared anywhere in the file.
+    UnknownVariable { name: String },
+    /// A variable name declared a second time.
+    DuplicateVariable { name: String },
+    /// No run of `slots` free variables left for the variable `name`.
+    OutOfVariables { name: String, slots: usize },
+    /// A constant defined a second time.
+    DuplicateConstant { name: String },
+    /// A constant named after an instruction.
+    ReservedName { name: String },
+    /// A label defined a second time, perhaps by a `rep` or by calling a routine again.
+    DuplicateLabel { name: String },
+    /// A variable name that is also the name of a constant.
+    ConstantVariable { name: String },
+    /// `rep`s inside each other, perhaps through a `call`, that together repeat their
+    /// code more than `max` times.
+    NestedRepeats { total: i64, max: i64 },
+    /// A file that could not be read as an object file.
+    InvalidObject { reason: &'static str },
+    /// A label jumped to by `object` that no linked object defines.
+    UndefinedImport { name: String, object: String },
+    /// A label jumped to from another object that is defined by more than one object.
+    AmbiguousLabel { name: String, first: String, second: String },
+    /// Variable names of two linked objects, declared at `first` and `second`, that share
+    /// the variable at `addr`.
+    OverlappingVariables { name: String, first: String, other: String, second: String, addr: u8 },
+    /// Two linked objects with different raw header bytes.
+    ConflictingHeader { first: String, second: String },
+}
+
+impl AscError {
+    /// `value` used where a byte is expected, if it does not fit in one.
+    pub(crate) fn byte_range(value: i64) -> Result<u8, AscError> {
+        u8::try_from(value).map_err(|_| AscError::OutOfRange { value, target: "byte", min: 0, max: 255 })
+    }
+
+    /// The stable code of this error, e.g. `E0101`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AscError::UnexpectedChar { .. } => "E0001",
+            AscError::InvalidLiteral { .. } => "E0002",
+            AscError::VarOutOfBounds { .. } => "E0003",
+            AscError::StringOverflow { .. } => "E0004",
+            AscError::UnexpectedEof { .. } => "E0005",
+            AscError::ArrayLength { .. } => "E0006",
+            AscError::InvalidArrayElement { .. } => "E0007",
+            AscError::UnterminatedComment { .. } => "E0008",
+            AscError::UnmappableChar { .. } => "E0009",
+            AscError::InvalidEscape { .. } => "E0010",
+            AscError::UnmatchedDelimiter { .. } => "E0011",
+            AscError::UnknownInstruction { .. } => "E0100",
+            AscError::UnexpectedToken { .. } => "E0101",
+            AscError::InvalidOperand { .. } => "E0102",
+            AscError::MissingOperand { .. } => "E0103",
+            AscError::UnknownLabel { .. } => "E0104",
+            AscError::UnknownRoutine { .. } => "E0105",
+            AscError::RecursiveRoutine { .. } => "E0106",
+            AscError::ConstantOverflow { .. } => "E0107",
+            AscError::LabelInHeader { .. } => "E0108",
+            AscError::OutOfRange { .. } => "E0109",
+            AscError::CodeInHeader { .. } => "E0110",
+            AscError::HeaderEnded { .. } => "E0111",
+            AscError::DuplicateHeaderFlag { .. } => "E0112",
+            AscError::NotInHeader { .. } => "E0113",
+            AscError::UnknownVariable { .. } => "E0114",
+            AscError::DuplicateVariable { .. } => "E0115",
+            AscError::OutOfVariables { .. } => "E0116",
+            AscError::DuplicateConstant { .. } => "E0117",
+            AscError::ReservedName { .. } => "E0118",
+            AscError::DuplicateLabel { .. } => "E0119",
+            AscError::ConstantVariable { .. } => "E0120",
+            AscError::NestedRepeats { .. } => "E0121",
+            AscError::InvalidObject { .. } => "E0200",
+            AscError::UndefinedImport { .. } => "E0201",
+            AscError::AmbiguousLabel { .. } => "E0202",
+            AscError::OverlappingVariables { .. } => "E0203",
+            AscError::ConflictingHeader { .. } => "E0204",
+        }
+    }
+}
+
+impl AscError {
+    /// The token that caused the error, for errors caused by one.
+    pub fn found(&self) -> Option<&TokenKind> {
+        match self {
+            AscError::InvalidArrayElement { found }
+            | AscError::UnexpectedToken { found }
+            | AscError::InvalidOperand { found, .. } => Some(found),
+            _ => None,
+        }
+    }
+
+    /// A hint on how to fix the error, shown below the source snippet.
+    pub fn help(&self) -> Option<String> {
+        match self {
+            AscError::InvalidOperand { inst, expected, .. } | AscError::MissingOperand { inst, expected } => {
+                Some(format!("{inst} expects `{expected}`"))
+            }
+            AscError::VarOutOfBounds { .. } => Some("variable addresses go from $00 to $ff".to_string()),
+            AscError::StringOverflow { .. } => Some("a string takes one variable for every 8 characters".to_string()),
+            AscError::UnmappableChar { .. } => {
+                Some("the console shows printable ASCII; write any other byte as `\\xNN`".to_string())
+            }
+            AscError::InvalidEscape { .. } => {
+                Some("escapes are `\\n`, `\\t`, `\\0`, `\\\\`, `\\\"`, `\\'` and `\\xNN`".to_string())
+            }
+            AscError::ArrayLength { .. } | AscError::InvalidArrayElement { .. } => {
+                Some("arrays hold exactly 8 `+byte`s, `$var`s or variable names".to_string())
+            }
+            AscError::UnknownLabel { name } => Some(format!("declare the label with `!{name}`")),
+            AscError::UnknownRoutine { name } => {
+                Some(format!("declare the routine with `routine {name} {{ ... }}` before calling it"))
+            }
+            AscError::LabelInHeader { .. } | AscError::CodeInHeader { .. } => {
+                Some("end the header with `endhead` first".to_string())
+            }
+            AscError::HeaderEnded { .. } => Some("move it before the `endhead`".to_string()),
+            AscError::DuplicateHeaderFlag { inst } => Some(format!("remove the second `{inst}`")),
+            AscError::NotInHeader { .. } => {
+                Some("a header block holds only `keeploop`, `keepopen` and `headerbytes`".to_string())
+            }
+            AscError::UnknownVariable { name } => Some(format!("declare the variable with `var {name} = ...`")),
+            AscError::DuplicateVariable { name } => {
+                Some(format!("set it again with `var {name} ...`, without the `=`"))
+            }
+            AscError::OutOfVariables { .. } => {
+                Some("give some variables the same address with `@ $var`".to_string())
+            }
+            AscError::DuplicateConstant { .. } => Some("give one of them another name".to_string()),
+            AscError::ReservedName { .. } => Some("instruction names cannot be used for constants".to_string()),
+            AscError::DuplicateLabel { .. } => {
+                Some("rename one of them; a label in a `rep` or a routine is defined each time it is expanded".to_string())
+            }
+            AscError::ConstantVariable { .. } => Some("rename the variable or the constant".to_string()),
+            AscError::NestedRepeats { .. } => {
+                Some("repeat the outer code with a label and a jump instead of a `rep`".to_string())
+            }
+            AscError::InvalidObject { .. } => Some("object files are written by the `object` command".to_string()),
+            AscError::UndefinedImport { name, .. } => {
+                Some(format!("declare the label with `!{name}` in one of the linked files"))
+            }
+            AscError::AmbiguousLabel { .. } => Some("rename the label in all but one of the files".to_string()),
+            AscError::OverlappingVariables { .. } => {
+                Some("pin one of the names elsewhere with `@`, or pass the other object when assembling its file".to_string())
+            }
+            AscError::ConflictingHeader { .. } => {
+                Some("give the raw header bytes with `headerbytes` in only one of the files".to_string())
+            }
+            AscError::OutOfRange { target, min, max, .. } => Some(format!("a {target} goes from {min} to {max}")),
+            AscError::UnterminatedComment { close, .. } => Some(format!("close the comment with `{close}`")),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AscError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AscError::UnexpectedChar { chr } => write!(f, "Unexpected character `{chr}`"),
+            AscError::InvalidLiteral { kind, text } => write!(f, "Invalid {kind} literal `{text}`"),
+            AscError::VarOutOfBounds { value } => {
+                write!(f, "Var accessor value out of bounds (Value was {value:x}, max is ff)")
+            }
+            AscError::StringOverflow { addr, slots } => {
+                write!(f, "String overflow (needs {slots} variables from ${addr:02x}, which runs past $ff)")
+            }
+            AscError::UnexpectedEof { context } => write!(f, "Unexpected EOF in {context}"),
+            AscError::ArrayLength { len } => write!(f, "Array length can only be 8, found {len} elements"),
+            AscError::InvalidArrayElement { found } => {
+                write!(f, "You can only have bytes or variable pointers in arrays, found `{found}`")
+            }
+            AscError::UnterminatedComment { line, .. } => write!(f, "Unterminated comment starting at line {line}"),
+            AscError::UnmappableChar { chr } => {
+                write!(f, "Character `{chr}` ({}) is not in the console character set", chr.escape_unicode())
+            }
+            AscError::InvalidEscape { text } => write!(f, "Unknown escape sequence `{text}`"),
+            AscError::UnmatchedDelimiter { found } => write!(f, "Unmatched `{found}`"),
+            AscError::UnknownInstruction { name } => write!(f, "Unknown instruction `{name}`"),
+            AscError::UnexpectedToken { found } => write!(f, "Unexpected token `{found}`"),
+            AscError::InvalidOperand { inst, found, .. } => write!(f, "Unexpected token `{found}` in {inst}"),
+            AscError::MissingOperand { inst, .. } => write!(f, "Missing operand in {inst}"),
+            AscError::UnknownLabel { name } => write!(f, "Unknown label `{name}`"),
+            AscError::UnknownRoutine { name } => write!(f, "Unknown routine `{name}`"),
+            AscError::RecursiveRoutine { name } => write!(f, "Routine `{name}` calls itself"),
+            AscError::ConstantOverflow { inst, lhs, rhs } => {
+                write!(f, "Constant {inst} of {lhs} and {rhs} overflows")
+            }
+            AscError::LabelInHeader { name } => {
+                write!(f, "Label `{name}` is defined before the end of the header")
+            }
+            AscError::OutOfRange { value, target, .. } => write!(f, "{value} does not fit in a {target}"),
+            AscError::CodeInHeader { inst } => write!(f, "`{inst}` is used before the end of the header"),
+            AscError::HeaderEnded { inst } => write!(f, "`{inst}` is used after the end of the header"),
+            AscError::DuplicateHeaderFlag { inst } => write!(f, "Header flag `{inst}` is set more than once"),
+            AscError::NotInHeader { inst } => write!(f, "`{inst}` cannot be used in a header block"),
+            AscError::UnknownVariable { name } => write!(f, "Unknown variable `{name}`"),
+            AscError::DuplicateVariable { name } => write!(f, "Variable `{name}` is declared more than once"),
+            AscError::OutOfVariables { name, slots } => {
+                write!(f, "No room for variable `{name}` (needs {slots} free variables in a row)")
+            }
+            AscError::DuplicateConstant { name } => write!(f, "Constant `{name}` is defined more than once"),
+            AscError::ReservedName { name } => write!(f, "`{name}` is an instruction, not a constant name"),
+            AscError::DuplicateLabel { name } => write!(f, "Label `{name}` is defined more than once"),
+            AscError::ConstantVariable { name } => write!(f, "`{name}` is both a constant and a variable name"),
+            AscError::NestedRepeats { total, max } => {
+                write!(f, "Nested `rep`s repeat their code {total} times, more than {max}")
+            }
+            AscError::InvalidObject { reason } => write!(f, "Not a valid object file: {reason}"),
+            AscError::UndefinedImport { name, object } => {
+                write!(f, "Label `{name}` used by `{object}` is not defined by any linked file")
+            }
+            AscError::AmbiguousLabel { name, first, second } => {
+                write!(f, "Label `{name}` is defined by both `{first}` and `{second}`")
+            }
+            AscError::OverlappingVariables { name, first, other, second, addr } => {
+                write!(f, "Variable `{name}` ({first}) and variable `{other}` ({second}) both use ${addr:02x}")
+            }
+            AscError::ConflictingHeader { first, second } => {
+                write!(f, "`{first}` and `{second}` set different raw header bytes")
+            }
+        }
+    }
+}
+
+impl Error for AscError {}
@@ -0,0 +1,65 @@
+//! The header at the start of every program: flags for the console, then the zero byte
+//! that ends it.
+
+use crate::isa::{FLAG_KEEP_LOOP, FLAG_KEEP_OPEN};
+
+/// The flags a program sets, built up by `header { ... }` or the header directives and
+/// written out in one go when the header ends.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Header {
+    /// Set by `keeploop`: run the program again when it reaches the end.
+    pub keep_loop: bool,
+    /// Set by `keepopen`: keep the window open when the program ends.
+    pub keep_open: bool,
+    /// Raw bytes from `headerbytes` other than those of the flags, written after the flags.
+    pub extra: Vec<u8>,
+}
+
+impl Header {
+    /// The header's flags in canonical order, `keeploop` first, then `keepopen`, then any
+    /// raw bytes.
+    pub fn flags(&self) -> Vec<u8> {
+        let mut flags = vec![];
+
+        if self.keep_loop {
+            flags.push(FLAG_KEEP_LOOP);
+        }
+        if self.keep_open {
+            flags.push(FLAG_KEEP_OPEN);
+        }
+        flags.extend(&self.extra);
+
+        flags
+    }
+
+    /// The header as written: its flags and the zero byte that ends it.
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes = self.flags();
+        bytes.push(0);
+        bytes
+    }
+
+    /// The number of bytes [`Header::bytes`] writes.
+    pub fn size(&self) -> usize {
+        usize::from(self.keep_loop) + usize::from(self.keep_open) + self.extra.len() + 1
+    }
+
+    /// The header whose [`Header::flags`] are `flags`. Flags out of canonical order are
+    /// kept as raw bytes.
+    pub fn decode(flags: &[u8]) -> Header {
+        let mut header = Header::default();
+        let mut rest = flags;
+
+        if let [FLAG_KEEP_LOOP, tail @ ..] = rest {
+            header.keep_loop = true;
+            rest = tail;
+        }
+        if let [FLAG_KEEP_OPEN, tail @ ..] = rest {
+            header.keep_open = true;
+            rest = tail;
+        }
+        header.extra = rest.to_vec();
+
+        header
+    }
+}
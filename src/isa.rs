@@ -0,0 +1,190 @@
+//! The instruction set: every mnemonic the assembler knows, the operands it takes and the
+//! opcode it compiles to.
+//!
+//! This table is the one description of the language the parser checks operands against,
+//! the code generator emits from and error messages are written from.
+//!
+//! Most instructions compile to their opcode followed by each operand in order, see
+//! [`OperandType::width`]. The exceptions are spelled out on their entries.
+
+use std::fmt;
+
+/// A bytecode type tag, written before the value of a `var`/`let`.
+pub const TAG_STR: u8 = 0xab;
+pub const TAG_NUM: u8 = 0xe0;
+pub const TAG_FLOAT: u8 = 0xf0;
+pub const TAG_ARRAY: u8 = 0x8a;
+
+/// The opcode of `var`, which constant folding also compiles to.
+pub const OP_VAR: u8 = 0xa1;
+
+/// Header flags, see [`crate::header::Header`].
+pub const FLAG_KEEP_LOOP: u8 = 0x02;
+pub const FLAG_KEEP_OPEN: u8 = 0x04;
+
+/// What an operand position accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandType {
+    /// A `$var` the instruction reads.
+    Var,
+    /// A `$var` the instruction writes to.
+    Out,
+    /// A `+byte`, or a number that fits in one.
+    Byte,
+    /// Any number of `+byte`s, up to the first token that is not one.
+    Bytes,
+    Num,
+    Float,
+    /// The initial value of a variable: a string, number, float or array.
+    Value,
+    /// A label to jump to.
+    Label,
+    /// The name of a routine.
+    Name,
+    Block,
+    Array,
+}
+
+impl OperandType {
+    /// How many bytes the operand takes up in bytecode, if that is fixed. Numbers, floats
+    /// and code addresses are 8 bytes little-endian, and a value is a type tag followed by
+    /// 8 bytes. A block stands for the 8-byte address of its end, followed by its code.
+    pub fn width(self) -> Option<usize> {
+        match self {
+            OperandType::Var | OperandType::Out | OperandType::Byte => Some(1),
+            OperandType::Num | OperandType::Float | OperandType::Label | OperandType::Array => Some(8),
+            OperandType::Block => Some(8),
+            OperandType::Value => Some(9),
+            OperandType::Bytes | OperandType::Name => None,
+        }
+    }
+}
+
+impl fmt::Display for OperandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OperandType::Var | OperandType::Out => "$var",
+            OperandType::Byte => "+byte",
+            OperandType::Bytes => "+byte...",
+            OperandType::Num => "num",
+            OperandType::Float => "#float",
+            OperandType::Value => "(\"str\" | num | #float | [array])",
+            OperandType::Label => "label",
+            OperandType::Name => "name",
+            OperandType::Block => "{block}",
+            OperandType::Array => "[array]",
+        })
+    }
+}
+
+/// One way of writing an instruction. The first operand picks the form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Form {
+    /// `None` if the form does not compile to an instruction of its own, like a `call`
+    /// that inlines a routine or arithmetic on constants that is folded away.
+    pub opcode: Option<u8>,
+    pub operands: &'static [OperandType],
+}
+
+impl Form {
+    /// How many bytes the form compiles to, not counting the code of a block.
+    pub fn size(&self) -> Option<usize> {
+        self.opcode?;
+
+        self.operands.iter().try_fold(1, |size, operand| Some(size + operand.width()?))
+    }
+}
+
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands: Vec<String> = self.operands.iter().map(OperandType::to_string).collect();
+
+        write!(f, "{}", operands.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    pub mnemonic: &'static str,
+    /// One line on what the instruction does.
+    pub summary: &'static str,
+    /// Whether it belongs in the header rather than the code.
+    pub header: bool,
+    pub forms: &'static [Form],
+}
+
+impl Instruction {
+    /// The operands of every form, e.g. `num num $var | $var $var $var`.
+    pub fn signature(&self) -> String {
+        let forms: Vec<String> = self.forms.iter().map(Form::to_string).collect();
+
+        forms.join(" | ")
+    }
+}
+
+const fn form(opcode: u8, operands: &'static [OperandType]) -> Form {
+    Form { opcode: Some(opcode), operands }
+}
+
+const fn inline(operands: &'static [OperandType]) -> Form {
+    Form { opcode: None, operands }
+}
+
+const fn code(mnemonic: &'static str, summary: &'static str, forms: &'static [Form]) -> Instruction {
+    Instruction { mnemonic, summary, header: false, forms }
+}
+
+const fn header(mnemonic: &'static str, summary: &'static str, forms: &'static [Form]) -> Instruction {
+    Instruction { mnemonic, summary, header: true, forms }
+}
+
+use OperandType::*;
+
+/// Every instruction, header directives first.
+pub const INSTRUCTIONS: &[Instruction] = &[
+    // The flags and `headerbytes` are gathered into a `Header`, written when it ends.
+    header("header", "Set the header flags in the block, then end the header", &[inline(&[Block])]),
+    header("keeploop", "Header flag: run the program again when it reaches the end", &[form(FLAG_KEEP_LOOP, &[])]),
+    header("keepopen", "Header flag: keep the window open when the program ends", &[form(FLAG_KEEP_OPEN, &[])]),
+    header("endhead", "End the header", &[form(0x00, &[])]),
+    header("headerbytes", "Write raw bytes into the header", &[inline(&[Bytes])]),
+    code("noop", "Do nothing", &[form(0x00, &[])]),
+    code("cpix", "Set the pixel at a constant x and y to a colour", &[form(0x01, &[Byte, Byte, Byte])]),
+    code("pix", "Set the pixel at the x and y held in variables to a colour", &[form(0x02, &[Var, Var, Byte])]),
+    code("spr", "Draw an 8x8 sprite, one variable per row, at x and y", &[form(0x03, &[Array, Var, Var])]),
+    // `var` and `let` write the value before the target address.
+    code("var", "Set a variable", &[form(OP_VAR, &[Out, Value])]),
+    code("let", "Set a variable only if it is empty", &[form(0xa2, &[Out, Value])]),
+    code("fdiv", "Divide two floats", &[inline(&[Float, Float, Out]), form(0xf0, &[Var, Var, Out])]),
+    code("fsub", "Subtract two floats", &[inline(&[Float, Float, Out]), form(0xf1, &[Var, Var, Out])]),
+    code("fadd", "Add two floats", &[inline(&[Float, Float, Out]), form(0xf2, &[Var, Var, Out])]),
+    code("fmul", "Multiply two floats", &[inline(&[Float, Float, Out]), form(0xf3, &[Var, Var, Out])]),
+    code("div", "Divide two numbers", &[inline(&[Num, Num, Out]), form(0xf4, &[Var, Var, Out])]),
+    code("sub", "Subtract two numbers", &[inline(&[Num, Num, Out]), form(0xf5, &[Var, Var, Out])]),
+    code("add", "Add two numbers", &[inline(&[Num, Num, Out]), form(0xf6, &[Var, Var, Out])]),
+    code("mul", "Multiply two numbers", &[inline(&[Num, Num, Out]), form(0xf7, &[Var, Var, Out])]),
+    code("tjmp", "Jump to a label if a variable is true", &[form(0xe1, &[Var, Label])]),
+    code("fjmp", "Jump to a label if a variable is false", &[form(0xe2, &[Var, Label])]),
+    code("jmp", "Jump to a label", &[form(0xe3, &[Label])]),
+    code("bjmp", "Jump to a raw code address", &[form(0xe3, &[Num])]),
+    code("gt", "Set a variable to whether one variable is greater than another", &[form(0xb1, &[Var, Var, Out])]),
+    code("lt", "Set a variable to whether one variable is less than another", &[form(0xb2, &[Var, Var, Out])]),
+    code("key", "Set a variable to whether a key is held", &[form(0xd0, &[Byte, Out])]),
+    code("routine", "Declare a routine, inlined by every `call` to it", &[inline(&[Name, Block])]),
+    code("call", "Inline a routine", &[inline(&[Name])]),
+    // An `fjmp` past the end of the block.
+    code("if", "Run a block if a variable is true", &[form(0xe2, &[Var, Block])]),
+    code("rep", "Repeat a block a constant number of times", &[inline(&[Num, Block])]),
+    code("flsh", "Draw the frame", &[form(0xfb, &[])]),
+    code("cls", "Clear the screen to a colour", &[form(0xfc, &[Byte])]),
+];
+
+/// The instruction written `mnemonic`.
+pub fn lookup(mnemonic: &str) -> Option<&'static Instruction> {
+    INSTRUCTIONS.iter().find(|instruction| instruction.mnemonic == mnemonic)
+}
+
+/// Every mnemonic, in table order.
+pub fn mnemonics() -> impl Iterator<Item = &'static str> {
+    INSTRUCTIONS.iter().map(|instruction| instruction.mnemonic)
+}
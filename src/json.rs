@@ -0,0 +1,76 @@
+//! Diagnostics as JSON, one object per line, for editors and CI:
+//!
+//! ```json
+//! {"code":"E0100","severity":"error","lint":null,"message":"Unknown instruction `pxi`","help":null,
+//!  "span":{"file":"main.asc","line":3,"column":5,"start":21,"end":24},
+//!  "suggestion":{"message":"did you mean `pix`?","replacement":"pix","span":{...}}}
+//! ```
+//!
+//! (Shown wrapped; each diagnostic is written on a single line.)
+
+use std::fmt::Write;
+
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::span::{SourceMap, Span};
+
+pub fn to_json(diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+    let lint = match &*diagnostic.kind {
+        DiagnosticKind::Lint(warning, _) => string(warning.lint().name()),
+        DiagnosticKind::Error(_) => "null".to_string(),
+    };
+
+    let suggestion = match &diagnostic.suggestion {
+        Some(suggestion) => format!(
+            "{{\"message\":{},\"replacement\":{},\"span\":{}}}",
+            string(&suggestion.message),
+            string(&suggestion.replacement),
+            span(suggestion.span, sources),
+        ),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"code\":{},\"severity\":{},\"lint\":{lint},\"message\":{},\"help\":{},\"span\":{},\"suggestion\":{suggestion}}}",
+        string(diagnostic.code()),
+        string(&diagnostic.severity.to_string()),
+        string(&diagnostic.message()),
+        diagnostic.help().map_or("null".to_string(), |help| string(&help)),
+        diagnostic.span.map_or("null".to_string(), |s| span(s, sources)),
+    )
+}
+
+fn span(span: Span, sources: &SourceMap) -> String {
+    format!(
+        "{{\"file\":{},\"line\":{},\"column\":{},\"start\":{},\"end\":{}}}",
+        string(sources.name(span.file)),
+        span.line,
+        span.col,
+        span.start,
+        span.end,
+    )
+}
+
+/// `text` as a JSON string literal.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+
+    out
+}
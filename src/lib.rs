@@ -0,0 +1,311 @@
+pub mod tokenizer;
+pub mod tokens;
+pub mod charset;
+pub mod ast;
+pub mod codegen;
+pub mod constants;
+pub mod debug;
+pub mod diagnostics;
+pub mod disasm;
+pub mod error;
+pub mod header;
+pub mod isa;
+pub mod json;
+pub mod link;
+pub mod lint;
+pub mod map;
+pub mod object;
+pub mod parser;
+pub mod render;
+pub mod resolve;
+pub mod span;
+
+use std::collections::HashMap;
+
+pub use debug::DebugInfo;
+pub use diagnostics::{Diagnostic, Diagnostics};
+pub use error::AscError;
+pub use link::link;
+pub use lint::{Level, Lint, LintLevels};
+pub use map::SymbolMap;
+pub use object::Object;
+pub use span::{FileId, SourceMap, Span};
+use debug::{Entry, Frame};
+use lint::{Usage, Warning};
+use map::{Expansion, Slot, Variable};
+use ast::{Block, OperandKind, Stmt};
+use codegen::{Fixup, Relocation};
+use header::Header;
+use parser::parse;
+use tokenizer::tokenize;
+
+/// Assembles `source` into ATC bytecode with a fresh [`Assembler`].
+pub fn assemble(source: &str) -> Result<Vec<u8>, Diagnostics> {
+    Assembler::new().assemble(source)
+}
+
+/// Holds the state of one assembly: the bytecode written so far, the labels and
+/// routines declared by the source and where the header ends.
+#[derive(Debug, Default)]
+pub struct Assembler {
+    /// Every file read so far. Kept across assemblies so spans in old diagnostics stay valid.
+    sources: SourceMap,
+    output: Vec<u8>,
+    labels: HashMap<String, usize>,
+    /// Jumps waiting for their label to be defined.
+    fixups: Vec<Fixup>,
+    /// Every code address written into `output`.
+    relocations: Vec<Relocation>,
+    routines: HashMap<String, Block>,
+    /// Routines read from object files by [`Assembler::import`]. Kept across assemblies.
+    library: HashMap<String, Block>,
+    /// Variable names declared by the objects read by [`Assembler::import`], whose
+    /// addresses are not given to other names. Kept across assemblies.
+    library_slots: Vec<Slot>,
+    /// The header flags set so far, written to `output` when the header ends.
+    header: Header,
+    /// Offset in `output` of code address 0, once the header has ended.
+    code_start: Option<usize>,
+    /// Every `call`, with `start` and `end` as offsets into `output`.
+    expansions: Vec<Expansion>,
+    /// Every variable set by a `var` or `let`, in order.
+    variables: Vec<Variable>,
+    /// Every variable name with the address it was given.
+    slots: Vec<Slot>,
+    /// Every instruction compiled, with offsets into `output`.
+    debug: Vec<Entry>,
+    /// The `call`s and `rep`s currently being expanded, outermost first.
+    frames: Vec<Frame>,
+    /// Routines currently being inlined by `call`, to catch self-recursion.
+    expanding: Vec<String>,
+    /// The counts of the `rep`s currently being unrolled, through any `call`s between them.
+    repeats: Vec<i64>,
+    /// Problems found so far by the current assembly.
+    diagnostics: Diagnostics,
+    /// The level each lint is reported at. Kept across assemblies.
+    lints: LintLevels,
+    usage: Usage,
+}
+
+impl Assembler {
+    pub fn new() -> Assembler {
+        Assembler::default()
+    }
+
+    /// Assembles `source`, returning the bytecode or every problem that was found.
+    ///
+    /// The assembler is reset first, so the same instance can be reused for several files.
+    pub fn assemble(&mut self, source: &str) -> Result<Vec<u8>, Diagnostics> {
+        self.assemble_file("<input>", source)
+    }
+
+    /// Like [`Assembler::assemble`], but records `name` as the file name in spans.
+    pub fn assemble_file(&mut self, name: &str, source: &str) -> Result<Vec<u8>, Diagnostics> {
+        self.run(name, source, false)?;
+
+        Ok(self.output.clone())
+    }
+
+    /// Assembles `source` into an [`Object`] to be linked with others by [`link`].
+    ///
+    /// Jumps to labels the file does not define are left for the linker rather than
+    /// reported, and labels and routines are not warned about for going unused, since
+    /// other files may use them.
+    pub fn assemble_object(&mut self, name: &str, source: &str) -> Result<Object, Diagnostics> {
+        self.run(name, source, true)?;
+
+        let code_start = self.code_start.unwrap_or(self.output.len());
+
+        let mut labels: Vec<(String, usize)> = self.labels.iter().map(|(name, &addr)| (name.clone(), addr)).collect();
+        labels.sort();
+
+        // A `call` in the header expands to header flags, which the object keeps apart from
+        // its code, like the debug entries below.
+        let expansions = self.expansions.iter()
+            .filter(|expansion| expansion.start >= code_start)
+            .map(|expansion| Expansion {
+                start: expansion.start - code_start,
+                end: expansion.end - code_start,
+                ..expansion.clone()
+            })
+            .collect();
+
+        let mut routines: Vec<String> = self.usage.routine_spans.iter().map(|(name, _)| name.clone()).collect();
+        routines.sort();
+        routines.dedup();
+
+        Ok(Object {
+            name: name.to_string(),
+            source: source.to_string(),
+            header: self.header.flags(),
+            code: self.output[code_start..].to_vec(),
+            labels,
+            relocations: self.relocations.iter()
+                .map(|relocation| Relocation { at: relocation.at - code_start, label: relocation.label.clone() })
+                .collect(),
+            routines,
+            expansions,
+            variables: self.variables.clone(),
+            slots: self.slots.clone(),
+            debug: self.debug.iter()
+                .filter(|entry| entry.offset >= code_start)
+                .map(|entry| Entry { offset: entry.offset - code_start, ..entry.clone() })
+                .collect(),
+        })
+    }
+
+    /// Makes the routines declared by `object` available to `call` in every later assembly,
+    /// and keeps the variable names it declares from being given the same addresses.
+    pub fn import(&mut self, object: &Object) -> Result<(), Diagnostics> {
+        let file = self.sources.add(object.name.clone(), object.source.clone());
+
+        let mut diagnostics = Diagnostics::new();
+        let tokens = tokenize(&object.source, file, &mut diagnostics);
+        let program = parse(tokens, &mut diagnostics);
+
+        if diagnostics.has_errors() {
+            return Err(diagnostics)
+        }
+
+        // The routines use the names of their own file, not of the files that call them.
+        let names: HashMap<String, u8> = object.slots.iter().map(|slot| (slot.name.clone(), slot.addr)).collect();
+
+        let mut stmts: Vec<&Stmt> = program.stmts.iter().collect();
+
+        // Routines may be declared inside blocks too.
+        while let Some(stmt) = stmts.pop() {
+            let Stmt::Instr(instr) = stmt else { continue };
+
+            for operand in &instr.operands {
+                if let OperandKind::Block(block) = &operand.kind {
+                    stmts.extend(&block.stmts);
+                }
+            }
+
+            if let ("routine", [routine, block]) = (instr.name.as_str(), &instr.operands[..])
+                && let (OperandKind::Name(routine), OperandKind::Block(block)) = (&routine.kind, &block.kind)
+                && object.routines.contains(routine)
+            {
+                let mut block = block.clone();
+                resolve::substitute(&mut block.stmts, &names, &mut diagnostics);
+                self.library.insert(routine.clone(), block);
+            }
+        }
+
+        if diagnostics.has_errors() {
+            return Err(diagnostics)
+        }
+
+        self.library_slots.extend(object.slots.iter().cloned());
+
+        Ok(())
+    }
+
+    /// Assembles `source` into `output`, for an object file if `object` is set.
+    fn run(&mut self, name: &str, source: &str, object: bool) -> Result<(), Diagnostics> {
+        let sources = std::mem::take(&mut self.sources);
+        let lints = std::mem::take(&mut self.lints);
+        let library = std::mem::take(&mut self.library);
+        let library_slots = std::mem::take(&mut self.library_slots);
+        *self = Assembler { sources, lints, routines: library.clone(), library, library_slots, ..Assembler::new() };
+
+        let file = self.sources.add(name, source);
+
+        let tokens = tokenize(source, file, &mut self.diagnostics);
+        let mut program = parse(tokens, &mut self.diagnostics);
+
+        self.resolve(&mut program.stmts);
+        self.compile(&program.stmts);
+
+        // A header that never ends is written as it is.
+        if self.code_start.is_none() {
+            self.output.extend(self.header.flags());
+        }
+
+        if !object {
+            self.unresolved_labels();
+        }
+
+        for (warning, span) in self.usage.warnings() {
+            if object && matches!(warning, Warning::UnusedLabel { .. } | Warning::UnusedRoutine { .. }) {
+                continue
+            }
+
+            self.warn(warning, span);
+        }
+
+        self.diagnostics.sort();
+
+        if self.diagnostics.has_errors() {
+            return Err(std::mem::take(&mut self.diagnostics))
+        }
+
+        Ok(())
+    }
+
+    /// The level each lint is reported at.
+    pub fn lints(&self) -> &LintLevels {
+        &self.lints
+    }
+
+    pub fn lints_mut(&mut self) -> &mut LintLevels {
+        &mut self.lints
+    }
+
+    /// Warnings from the last successful assembly. A failed assembly returns its
+    /// warnings along with its errors.
+    pub fn warnings(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Every file read by this assembler, for resolving spans.
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Code addresses of every label seen by the last assembly.
+    pub fn labels(&self) -> &HashMap<String, usize> {
+        &self.labels
+    }
+
+    /// Bodies of every routine declared by the last assembly.
+    pub fn routines(&self) -> &HashMap<String, Block> {
+        &self.routines
+    }
+
+    /// Size in bytes of the header written by the last assembly, including the zero byte
+    /// that ends it.
+    pub fn header_size(&self) -> usize {
+        self.code_start.unwrap_or(self.output.len())
+    }
+
+    /// Where the labels, routine expansions and variables of the last assembly ended up.
+    pub fn symbol_map(&self) -> SymbolMap {
+        let code_start = self.header_size();
+
+        let labels = self.labels.iter().map(|(name, &addr)| (name.clone(), addr)).collect();
+
+        let expansions = self.expansions.iter()
+            .map(|expansion| Expansion {
+                start: expansion.start.saturating_sub(code_start),
+                end: expansion.end.saturating_sub(code_start),
+                ..expansion.clone()
+            })
+            .collect();
+
+        SymbolMap::new(code_start, labels, expansions, self.variables.clone(), self.slots.clone())
+    }
+
+    /// Where every instruction of the last assembly came from.
+    pub fn debug_info(&self) -> DebugInfo {
+        let mut entries = self.debug.clone();
+        entries.sort_by_key(|entry| entry.offset);
+
+        DebugInfo { entries }
+    }
+
+    /// Every code address written by the last assembly.
+    pub fn relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+}
@@ -0,0 +1,188 @@
+//! Combines [`Object`]s into one ATC program.
+//!
+//! The program gets a single header holding every flag set by any object, each once, and
+//! the raw header bytes of the objects that have any, and then the code of each object in
+//! the order given, so the first object is the one that runs first. Every
+//! [`Relocation`](crate::codegen::Relocation) is then moved by where its object's code
+//! ended up, and jumps to labels an object did not define are pointed at the one object
+//! that does.
+
+use std::collections::HashMap;
+
+use crate::debug::{DebugInfo, Entry};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::AscError;
+use crate::header::Header;
+use crate::map::{Expansion, SymbolMap};
+use crate::object::Object;
+
+/// The linked header: every flag set by `objects`, each once, and the raw header bytes
+/// of the objects that have any, as written. Objects with different raw bytes are
+/// reported, as only one set can be kept.
+fn header(objects: &[Object], diagnostics: &mut Diagnostics) -> Header {
+    let mut header = Header::default();
+    let mut raw: Option<&str> = None;
+
+    for object in objects {
+        let flags = Header::decode(&object.header);
+
+        header.keep_loop |= flags.keep_loop;
+        header.keep_open |= flags.keep_open;
+
+        if flags.extra.is_empty() {
+            continue
+        }
+
+        match raw {
+            None => {
+                header.extra = flags.extra;
+                raw = Some(&object.name);
+            }
+            Some(first) if flags.extra != header.extra => {
+                let error = AscError::ConflictingHeader { first: first.to_string(), second: object.name.clone() };
+                diagnostics.report(Diagnostic::new(error));
+            }
+            Some(_) => {}
+        }
+    }
+
+    header
+}
+
+/// The code address each object's code is linked at.
+fn bases(objects: &[Object]) -> Vec<usize> {
+    objects.iter()
+        .scan(0, |base, object| {
+            let start = *base;
+            *base += object.code.len();
+            Some(start)
+        })
+        .collect()
+}
+
+/// Reports every pair of variable names from different objects that share a variable,
+/// unless both are the same name at the same addresses.
+fn overlaps(objects: &[Object], diagnostics: &mut Diagnostics) {
+    for (i, object) in objects.iter().enumerate() {
+        for other in &objects[i + 1..] {
+            for first in &object.slots {
+                for second in &other.slots {
+                    let shared = first.name == second.name && first.addr == second.addr && first.len == second.len;
+
+                    let start = first.addr.max(second.addr) as usize;
+                    let end = (first.addr as usize + first.len).min(second.addr as usize + second.len);
+
+                    if !shared && start < end {
+                        let error = AscError::OverlappingVariables {
+                            name: first.name.clone(),
+                            first: first.site.clone(),
+                            other: second.name.clone(),
+                            second: second.site.clone(),
+                            addr: start as u8,
+                        };
+                        diagnostics.report(Diagnostic::new(error));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Links `objects` into ATC bytecode, or returns every label that could not be resolved
+/// and every variable two objects' names share.
+pub fn link(objects: &[Object]) -> Result<Vec<u8>, Diagnostics> {
+    let mut diagnostics = Diagnostics::new();
+
+    overlaps(objects, &mut diagnostics);
+
+    let mut output = header(objects, &mut diagnostics).bytes();
+
+    let code_start = output.len();
+
+    // Every label with the objects defining it.
+    let mut symbols: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
+
+    for (object, base) in objects.iter().zip(bases(objects)) {
+        for (name, addr) in &object.labels {
+            symbols.entry(name).or_default().push((base + addr, &object.name));
+        }
+
+        output.extend(&object.code);
+    }
+
+    for (object, base) in objects.iter().zip(bases(objects)) {
+        for relocation in &object.relocations {
+            let at = code_start + base + relocation.at;
+
+            let addr = match &relocation.label {
+                Some(label) if object.label(label).is_none() => {
+                    match symbols.get(label.as_str()).map(Vec::as_slice) {
+                        Some(&[(addr, _)]) => addr,
+                        Some(&[(_, first), (_, second), ..]) => {
+                            let error = AscError::AmbiguousLabel {
+                                name: label.clone(),
+                                first: first.to_string(),
+                                second: second.to_string(),
+                            };
+                            diagnostics.report(Diagnostic::new(error));
+                            continue
+                        }
+                        _ => {
+                            let error = AscError::UndefinedImport { name: label.clone(), object: object.name.clone() };
+                            diagnostics.report(Diagnostic::new(error));
+                            continue
+                        }
+                    }
+                }
+                // Already resolved within the object, relative to the start of its code.
+                _ => base + u64::from_le_bytes(output[at..at + 8].try_into().unwrap()) as usize,
+            };
+
+            output[at..at + 8].copy_from_slice(&addr.to_le_bytes());
+        }
+    }
+
+    if diagnostics.has_errors() {
+        return Err(diagnostics)
+    }
+
+    Ok(output)
+}
+
+/// The symbol map of the program [`link`] makes out of `objects`. Variables set by more
+/// than one object are listed with the value set by the first.
+pub fn link_map(objects: &[Object]) -> SymbolMap {
+    let mut labels = vec![];
+    let mut expansions = vec![];
+    let mut variables = vec![];
+    let mut slots = vec![];
+
+    for (object, base) in objects.iter().zip(bases(objects)) {
+        labels.extend(object.labels.iter().map(|(name, addr)| (name.clone(), base + addr)));
+
+        expansions.extend(object.expansions.iter().map(|expansion| Expansion {
+            start: base + expansion.start,
+            end: base + expansion.end,
+            ..expansion.clone()
+        }));
+
+        variables.extend(object.variables.iter().cloned());
+        slots.extend(object.slots.iter().cloned());
+    }
+
+    SymbolMap::new(header(objects, &mut Diagnostics::new()).size(), labels, expansions, variables, slots)
+}
+
+/// The debug info of the program [`link`] makes out of `objects`.
+pub fn link_debug_info(objects: &[Object]) -> DebugInfo {
+    let code_start = header(objects, &mut Diagnostics::new()).size();
+
+    let entries = objects.iter()
+        .zip(bases(objects))
+        .flat_map(|(object, base)| {
+            object.debug.iter().map(move |entry| Entry { offset: code_start + base + entry.offset, ..entry.clone() })
+        })
+        .collect();
+
+    DebugInfo { entries }
+}
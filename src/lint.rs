@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::diagnostics::Suggestion;
+use crate::span::Span;
+
+/// A check that produces warnings. Each lint can be allowed, warned about or denied
+/// (turned into an error) on its own, see [`LintLevels`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnreachableCode,
+    UnusedLabel,
+    RedundantLet,
+    UnusedRoutine,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedVariable,
+        Lint::UnreachableCode,
+        Lint::UnusedLabel,
+        Lint::RedundantLet,
+        Lint::UnusedRoutine,
+    ];
+
+    /// The name used to refer to the lint on the command line, e.g. `unused-label`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::UnusedLabel => "unused-label",
+            Lint::RedundantLet => "redundant-let",
+            Lint::UnusedRoutine => "unused-routine",
+        }
+    }
+
+    /// The stable code of the lint, e.g. `W0001`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "W0001",
+            Lint::UnreachableCode => "W0002",
+            Lint::UnusedLabel => "W0003",
+            Lint::RedundantLet => "W0004",
+            Lint::UnusedRoutine => "W0005",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The level every lint is reported at. Lints warn unless told otherwise.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LintLevels {
+    /// Each lint's level, with the name it was set by: its own or `warnings`.
+    levels: HashMap<Lint, (Level, &'static str)>,
+}
+
+impl LintLevels {
+    pub fn new() -> LintLevels {
+        LintLevels::default()
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, (level, lint.name()));
+    }
+
+    /// Sets every lint to `level`, as `-W/-A/-D warnings` does.
+    pub fn set_all(&mut self, level: Level) {
+        for lint in Lint::ALL {
+            self.levels.insert(lint, (level, "warnings"));
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).map_or(Level::Warn, |&(level, _)| level)
+    }
+
+    /// The name the level of `lint` was last set by, e.g. `warnings` after `-D warnings`.
+    pub fn set_by(&self, lint: Lint) -> &'static str {
+        self.levels.get(&lint).map_or(lint.name(), |&(_, name)| name)
+    }
+}
+
+/// Something suspicious that still assembles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A variable set by `var`/`let` that nothing reads.
+    UnusedVariable { addr: u8 },
+    /// A statement following an unconditional jump with no label in between.
+    UnreachableCode,
+    /// A label that nothing jumps to.
+    UnusedLabel { name: String },
+    /// A `let` to an address an earlier `var` already set, so it never does anything.
+    RedundantLet { addr: u8 },
+    /// A routine that is never `call`ed.
+    UnusedRoutine { name: String },
+}
+
+impl Warning {
+    pub fn lint(&self) -> Lint {
+        match self {
+            Warning::UnusedVariable { .. } => Lint::UnusedVariable,
+            Warning::UnreachableCode => Lint::UnreachableCode,
+            Warning::UnusedLabel { .. } => Lint::UnusedLabel,
+            Warning::RedundantLet { .. } => Lint::RedundantLet,
+            Warning::UnusedRoutine { .. } => Lint::UnusedRoutine,
+        }
+    }
+
+    pub fn help(&self) -> Option<String> {
+        match self {
+            Warning::UnreachableCode => Some("add a label before this code if something should jump to it".to_string()),
+            Warning::RedundantLet { addr } => Some(format!("`let` only sets ${addr:02x} if it is empty; use `var` to overwrite it")),
+            _ => None,
+        }
+    }
+
+    /// A fix for the warning reported at `span`.
+    pub fn suggestion(&self, span: Span) -> Option<Suggestion> {
+        match self {
+            Warning::RedundantLet { .. } => Some(Suggestion {
+                message: "use `var` to overwrite the variable".to_string(),
+                span,
+                replacement: "var".to_string(),
+            }),
+            Warning::UnusedLabel { .. } => Some(Suggestion {
+                message: "remove the label".to_string(),
+                span,
+                replacement: String::new(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnusedVariable { addr } => write!(f, "Variable ${addr:02x} is set but never read"),
+            Warning::UnreachableCode => write!(f, "Unreachable code after unconditional jump"),
+            Warning::UnusedLabel { name } => write!(f, "Label `{name}` is never jumped to"),
+            Warning::RedundantLet { addr } => write!(f, "`let` of ${addr:02x} has no effect, it was already set by `var`"),
+            Warning::UnusedRoutine { name } => write!(f, "Routine `{name}` is never called"),
+        }
+    }
+}
+
+/// What the compiler saw being declared and used, for the lints that need the whole
+/// file before they can decide anything.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Usage {
+    /// Variables read as an input by some instruction.
+    pub reads: HashSet<u8>,
+    /// Every `var`/`let`, in source order, with where it was written.
+    pub inits: Vec<(u8, Span)>,
+    /// Addresses an earlier `var` has set, which makes a later `let` a no-op.
+    pub assigned: HashSet<u8>,
+    /// Labels named by some jump, whether or not they exist.
+    pub jumped: HashSet<String>,
+    /// Routines named by some `call`, whether or not they exist.
+    pub called: HashSet<String>,
+    pub label_spans: Vec<(String, Span)>,
+    pub routine_spans: Vec<(String, Span)>,
+}
+
+impl Usage {
+    pub fn read(&mut self, addr: u8) {
+        self.reads.insert(addr);
+    }
+
+    /// The warnings that can only be given once the whole file has been compiled.
+    pub fn warnings(&self) -> Vec<(Warning, Span)> {
+        let mut warnings = vec![];
+        let mut seen = HashSet::new();
+
+        for (addr, span) in &self.inits {
+            if !self.reads.contains(addr) && seen.insert(*addr) {
+                warnings.push((Warning::UnusedVariable { addr: *addr }, *span));
+            }
+        }
+
+        for (name, span) in &self.label_spans {
+            if !self.jumped.contains(name) {
+                warnings.push((Warning::UnusedLabel { name: name.clone() }, *span));
+            }
+        }
+
+        for (name, span) in &self.routine_spans {
+            if !self.called.contains(name) {
+                warnings.push((Warning::UnusedRoutine { name: name.clone() }, *span));
+            }
+        }
+
+        warnings
+    }
+}
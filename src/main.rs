@@ -1,34 +1,485 @@
-#![feature(let_else)]
-#![feature(let_chains)]
-
-pub mod tokenizer;
-pub mod tokens;
-pub mod compiler;
-
-use std::collections::HashMap;
+use std::env;
+use std::io::{IsTerminal, stderr};
+use std::fs::read;
 use std::fs::read_to_string;
 use std::fs::write;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::process::Command as Process;
+
+use asc_script::disasm::disassemble;
+use asc_script::{Assembler, DebugInfo, Diagnostics, FileId, Level, Lint, LintLevels, Object, SourceMap, SymbolMap, isa, link};
+use asc_script::link::{link_debug_info, link_map};
+use asc_script::tokenizer::tokenize;
+
+const USAGE: &str = "\
+Usage: asc_script [COMMAND] [OPTIONS] [INPUT] [OBJECT...]
+
+Commands:
+    build       Assemble INPUT into ATC bytecode (default)
+    check       Assemble INPUT without writing any output
+    object      Assemble INPUT into an object file to link later
+    disasm      Disassemble an .atc file INPUT back into source
+    run         Assemble INPUT and launch it in the ATC console
+    isa         List every instruction with its operands and opcode
+
+Options:
+    -o, --output <PATH>     Where to write the bytecode (default: INPUT with an .atc extension).
+                            A symbol map is written next to it with a .map extension
+    -g, --debug-info        Also write where each instruction came from next to the
+                            bytecode, with a .dbg extension added
+        --console <PATH>    ATC console binary used by `run` (default: ./atc_console)
+        --color <WHEN>      Colour diagnostics: auto, always or never (default: auto)
+        --message-format <FMT>
+                            Diagnostic format: human, or json for one JSON object per
+                            line on stdout (default: human)
+    -W, --warn <LINT>       Report LINT as a warning
+    -A, --allow <LINT>      Silence LINT
+    -D, --deny <LINT>       Report LINT as an error
+    -q, --quiet             Only print errors
+    -v, --verbose           Print the token stream and other progress information
+    -h, --help              Print this message
+
+INPUT defaults to main.asc, and may itself be an object file. Each OBJECT is linked
+after INPUT, and the routines it declares can be called from INPUT. LINT is one of
+unused-variable, unreachable-code, unused-label, redundant-let, unused-routine, or
+`warnings` for all of them.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Build,
+    Check,
+    Object,
+    Disasm,
+    Run,
+    Isa,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    input: PathBuf,
+    /// Object files to link after `input`.
+    objects: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    debug_info: bool,
+    console: PathBuf,
+    verbosity: Verbosity,
+    color: Color,
+    message_format: MessageFormat,
+    lints: LintLevels,
+}
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Options, String> {
+        // `--flag=value` is the same as `--flag value`.
+        let mut args = args.flat_map(|arg| match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => vec![flag.to_string(), value.to_string()],
+            _ => vec![arg],
+        });
+
+        let mut command = None;
+        let mut input = None;
+        let mut objects = vec![];
+        let mut output = None;
+        let mut debug_info = false;
+        let mut console = PathBuf::from("./atc_console");
+        let mut verbosity = Verbosity::Normal;
+        let mut color = Color::Auto;
+        let mut message_format = MessageFormat::Human;
+        let mut lints = LintLevels::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    exit(0)
+                }
+                "-o" | "--output" => {
+                    let Some(path) = args.next() else {
+                        return Err(format!("`{arg}` expects a path"))
+                    };
+                    output = Some(PathBuf::from(path));
+                }
+                "-g" | "--debug-info" => debug_info = true,
+                "--console" => {
+                    let Some(path) = args.next() else {
+                        return Err(format!("`{arg}` expects a path"))
+                    };
+                    console = PathBuf::from(path);
+                }
+                "--color" => {
+                    color = match args.next().as_deref() {
+                        Some("auto") => Color::Auto,
+                        Some("always") => Color::Always,
+                        Some("never") => Color::Never,
+                        _ => return Err(format!("`{arg}` expects one of auto, always or never")),
+                    };
+                }
+                "--message-format" => {
+                    message_format = match args.next().as_deref() {
+                        Some("human") => MessageFormat::Human,
+                        Some("json") => MessageFormat::Json,
+                        _ => return Err(format!("`{arg}` expects one of human or json")),
+                    };
+                }
+                "-W" | "--warn" | "-A" | "--allow" | "-D" | "--deny" => {
+                    let level = match arg.as_str() {
+                        "-W" | "--warn" => Level::Warn,
+                        "-A" | "--allow" => Level::Allow,
+                        _ => Level::Deny,
+                    };
 
-use tokenizer::tokenize;
+                    match args.next().as_deref() {
+                        Some("warnings") => lints.set_all(level),
+                        Some(name) => {
+                            let Some(lint) = Lint::from_name(name) else {
+                                return Err(format!("Unknown lint `{name}`"))
+                            };
+                            lints.set(lint, level)
+                        }
+                        None => return Err(format!("`{arg}` expects a lint name")),
+                    }
+                }
+                "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+                flag if flag.starts_with('-') && flag != "-" => {
+                    return Err(format!("Unknown option `{flag}`"))
+                }
+                "build" if command.is_none() && input.is_none() => command = Some(Command::Build),
+                "check" if command.is_none() && input.is_none() => command = Some(Command::Check),
+                "object" if command.is_none() && input.is_none() => command = Some(Command::Object),
+                "disasm" if command.is_none() && input.is_none() => command = Some(Command::Disasm),
+                "run" if command.is_none() && input.is_none() => command = Some(Command::Run),
+                "isa" if command.is_none() && input.is_none() => command = Some(Command::Isa),
+                path if input.is_none() => input = Some(PathBuf::from(path)),
+                path => objects.push(PathBuf::from(path)),
+            }
+        }
 
-use crate::compiler::compile;
+        Ok(Options {
+            command: command.unwrap_or(Command::Build),
+            input: input.unwrap_or_else(|| PathBuf::from("main.asc")),
+            objects,
+            output,
+            debug_info,
+            console,
+            verbosity,
+            color,
+            message_format,
+            lints,
+        })
+    }
+
+    fn use_color(&self) -> bool {
+        match self.color {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// Where to write the output: `-o`, or else INPUT with `extension`.
+    fn output_path(&self, extension: &str) -> PathBuf {
+        match &self.output {
+            Some(path) => path.clone(),
+            None => self.input.with_extension(extension),
+        }
+    }
+}
 
 fn main() {
-    let file = read_to_string("main.asc").unwrap();
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            exit(2)
+        }
+    };
+
+    if let Err(err) = execute(&options) {
+        eprintln!("{err}");
+        exit(1)
+    }
+}
+
+fn execute(options: &Options) -> Result<(), String> {
+    match options.command {
+        Command::Build => {
+            let program = assemble(options)?;
+            let path = options.output_path("atc");
+
+            program.write(&path, options)?;
+
+            if options.verbosity >= Verbosity::Normal {
+                eprintln!("Assembled {} -> {} ({} bytes)", options.input.display(), path.display(), program.output.len());
+            }
+        }
+        Command::Check => {
+            assemble(options)?;
+
+            if options.verbosity >= Verbosity::Normal {
+                eprintln!("{} is OK", options.input.display());
+            }
+        }
+        Command::Object => {
+            let object = assemble_object(options)?;
+            let path = options.output_path("o");
+
+            write(&path, object.to_bytes())
+                .map_err(|err| format!("error: Could not write `{}`: {err}", path.display()))?;
 
-    let tokens = tokenize(file);
+            if options.verbosity >= Verbosity::Normal {
+                eprintln!(
+                    "Assembled {} -> {} ({} bytes of code, {})",
+                    options.input.display(),
+                    path.display(),
+                    object.code.len(),
+                    plural(object.imports().len(), "import"),
+                );
+            }
+        }
+        Command::Disasm => {
+            let bytes = read(&options.input)
+                .map_err(|err| format!("error: Could not read `{}`: {err}", options.input.display()))?;
 
-    println!("{tokens:?}");
+            let source = disassemble(&bytes);
+
+            match &options.output {
+                Some(path) => {
+                    write(path, source).map_err(|err| format!("error: Could not write `{}`: {err}", path.display()))?
+                }
+                None => print!("{source}"),
+            }
+        }
+        Command::Run => {
+            let program = assemble(options)?;
+            let path = options.output_path("atc");
+
+            program.write(&path, options)?;
+
+            if options.verbosity >= Verbosity::Verbose {
+                eprintln!("Launching {} {}", options.console.display(), path.display());
+            }
+
+            let status = Process::new(&options.console)
+                .arg(&path)
+                .status()
+                .map_err(|err| format!("error: Could not launch `{}`: {err}", options.console.display()))?;
+
+            if !status.success() {
+                return Err(format!("error: ATC console exited with {status}"))
+            }
+        }
+        Command::Isa => {
+            for instruction in isa::INSTRUCTIONS {
+                for form in instruction.forms {
+                    let opcode = form.opcode.map_or("--".to_string(), |opcode| format!("{opcode:02x}"));
+                    let size = form.size().map_or("-".to_string(), |size| size.to_string());
+                    let syntax = format!("{} {form}", instruction.mnemonic);
+
+                    println!("{opcode}  {size:>2}  {syntax:<42}  {}", instruction.summary);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// An assembled program and what is written next to it.
+struct Program {
+    output: Vec<u8>,
+    map: SymbolMap,
+    debug_info: DebugInfo,
+}
 
-    let mut labels = HashMap::new();
+impl Program {
+    /// Writes the bytecode to `path`, the symbol map next to it, and the debug info if
+    /// asked for.
+    fn write(&self, path: &Path, options: &Options) -> Result<(), String> {
+        let write = |path: &Path, contents: &[u8]| {
+            write(path, contents).map_err(|err| format!("error: Could not write `{}`: {err}", path.display()))
+        };
 
-    let mut routines = HashMap::new();
+        write(path, &self.output)?;
+        write(&path.with_extension("map"), self.map.to_string().as_bytes())?;
 
-    let mut output = Vec::new();
+        if options.debug_info {
+            let mut dbg_path = path.as_os_str().to_owned();
+            dbg_path.push(".dbg");
 
-    let mut header_size: usize = 0;
+            write(Path::new(&dbg_path), self.debug_info.to_string().as_bytes())?;
+        }
 
-    compile(tokens, &mut output, &mut labels, &mut routines, &mut header_size);
+        Ok(())
+    }
+}
+
+/// Assembles INPUT, linking it with every OBJECT if there are any.
+fn assemble(options: &Options) -> Result<Program, String> {
+    if options.objects.is_empty() && !is_object(&options.input) {
+        let (mut assembler, file) = prepare(options, &[])?;
+
+        let result = assembler.assemble_file(&options.input.display().to_string(), &file)
+            .map(|output| Program { output, map: assembler.symbol_map(), debug_info: assembler.debug_info() });
+
+        return finish(options, "assemble", assembler.sources(), assembler.warnings(), result)
+    }
+
+    let mut objects = vec![assemble_object(options)?];
+
+    for path in &options.objects {
+        objects.push(read_object(path)?);
+    }
+
+    let result = link(&objects)
+        .map(|output| Program { output, map: link_map(&objects), debug_info: link_debug_info(&objects) });
+
+    finish(options, "link", &SourceMap::new(), &Diagnostics::new(), result)
+}
+
+/// Assembles INPUT into an object file, or reads it if it already is one.
+fn assemble_object(options: &Options) -> Result<Object, String> {
+    if is_object(&options.input) {
+        return read_object(&options.input)
+    }
+
+    let mut libraries = vec![];
+
+    for path in &options.objects {
+        libraries.push(read_object(path)?);
+    }
+
+    let (mut assembler, file) = prepare(options, &libraries)?;
+
+    let result = assembler.assemble_object(&options.input.display().to_string(), &file);
+
+    finish(options, "assemble", assembler.sources(), assembler.warnings(), result)
+}
+
+fn is_object(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "o")
+}
+
+fn read_object(path: &Path) -> Result<Object, String> {
+    let bytes = read(path).map_err(|err| format!("error: Could not read `{}`: {err}", path.display()))?;
+
+    Object::from_bytes(&bytes).map_err(|err| format!("error: Could not read `{}`: {err}", path.display()))
+}
+
+/// Reads INPUT and sets up an assembler for it that can call the routines of `libraries`.
+fn prepare(options: &Options, libraries: &[Object]) -> Result<(Assembler, String), String> {
+    let file = read_to_string(&options.input)
+        .map_err(|err| format!("error: Could not read `{}`: {err}", options.input.display()))?;
+
+    if options.verbosity >= Verbosity::Verbose {
+        let tokens = tokenize(&file, FileId(0), &mut Diagnostics::new());
+        eprintln!("{tokens:?}");
+    }
+
+    let mut assembler = Assembler::new();
+
+    *assembler.lints_mut() = options.lints.clone();
+
+    for library in libraries {
+        // Only fails for an object file that was edited by hand, as its source assembled.
+        assembler.import(library)
+            .map_err(|_| format!("error: Could not read the routines of `{}`", library.name))?;
+    }
+
+    Ok((assembler, file))
+}
+
+/// Prints the warnings of a successful `verb` (e.g. assemble), or turns the diagnostics of
+/// a failed one into a report.
+fn finish<T>(
+    options: &Options,
+    verb: &str,
+    sources: &SourceMap,
+    warnings: &Diagnostics,
+    result: Result<T, Diagnostics>,
+) -> Result<T, String> {
+    let color = options.use_color();
+
+    // JSON goes straight to stdout a line at a time; human output is collected into a
+    // report for stderr.
+    let render = |diagnostics: &Diagnostics| -> Vec<String> {
+        match options.message_format {
+            MessageFormat::Human => diagnostics.iter()
+                .map(|diagnostic| diagnostic.render(sources, color))
+                .collect(),
+            MessageFormat::Json => {
+                for diagnostic in diagnostics {
+                    println!("{}", diagnostic.to_json(sources));
+                }
+                vec![]
+            }
+        }
+    };
+
+    match result {
+        Ok(output) => {
+            if options.message_format == MessageFormat::Json {
+                render(warnings);
+            } else if !warnings.is_empty() && options.verbosity >= Verbosity::Normal {
+                let mut report = render(warnings);
+
+                report.push(format!(
+                    "warning: `{}` generated {}",
+                    options.input.display(),
+                    plural(warnings.len(), "warning"),
+                ));
+
+                eprintln!("{}\n", report.join("\n\n"));
+            }
+
+            Ok(output)
+        }
+        Err(diagnostics) => {
+            let mut report = render(&diagnostics);
+
+            let mut summary = format!(
+                "error: could not {verb} `{}` due to {}",
+                options.input.display(),
+                plural(diagnostics.error_count(), "previous error"),
+            );
+
+            if diagnostics.warning_count() != 0 {
+                summary += &format!("; {} emitted", plural(diagnostics.warning_count(), "warning"));
+            }
+
+            report.push(summary);
+
+            Err(report.join("\n\n"))
+        }
+    }
+}
 
-    write("./out.atc", output).unwrap();
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
 }
@@ -0,0 +1,138 @@
+//! Symbol maps: where everything ended up in an assembled program, written next to the
+//! bytecode as a `.map` file so dumps and console logs can be read without counting bytes.
+//!
+//! ```text
+//! header: 2 bytes, code address 0 is file offset 0x0002
+//!
+//! labels:
+//!     0x000b  back
+//!
+//! routines:
+//!     0x000b..0x000e  clear  called at game.asc:5
+//!
+//! variables:
+//!     $00  num    1  set at game.asc:3
+//!
+//! names:
+//!     $00      score  allocated at game.asc:2
+//!     $01-$02  title  allocated at game.asc:4
+//!     $40      lives  pinned at game.asc:3
+//! ```
+
+use std::cmp::Reverse;
+use std::fmt;
+
+/// One `call` of a routine, inlined at code addresses `start..end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub routine: String,
+    pub start: usize,
+    pub end: usize,
+    /// The file and line of the `call`, e.g. `game.asc:5`.
+    pub site: String,
+}
+
+/// The value a `var` or `let` first sets a variable to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub addr: u8,
+    /// `num`, `float`, `str` or `array`.
+    pub kind: String,
+    /// The value as it would be written in source.
+    pub value: String,
+    /// The file and line of the `var` or `let`.
+    pub site: String,
+}
+
+/// A variable name and the addresses it stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub name: String,
+    pub addr: u8,
+    /// How many variables from `addr` it takes up, more than one for a long string.
+    pub len: usize,
+    /// Whether the address was given with `@` rather than allocated.
+    pub pinned: bool,
+    /// The file and line of the declaration.
+    pub site: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SymbolMap {
+    /// Size of the header, including the zero byte that ends it.
+    pub header_size: usize,
+    /// Every label with its code address, in address order.
+    pub labels: Vec<(String, usize)>,
+    /// Every routine expansion, in address order.
+    pub expansions: Vec<Expansion>,
+    /// The first value set for every variable, in address order.
+    pub variables: Vec<Variable>,
+    /// Every variable name, in address order.
+    pub slots: Vec<Slot>,
+}
+
+impl SymbolMap {
+    /// Sorts everything by address, keeping only the first value set for each variable.
+    pub fn new(
+        header_size: usize,
+        mut labels: Vec<(String, usize)>,
+        mut expansions: Vec<Expansion>,
+        variables: Vec<Variable>,
+        mut slots: Vec<Slot>,
+    ) -> SymbolMap {
+        labels.sort_by(|(a, a_addr), (b, b_addr)| a_addr.cmp(b_addr).then_with(|| a.cmp(b)));
+        // A routine called by another starts where its caller does but ends sooner.
+        expansions.sort_by_key(|expansion| (expansion.start, Reverse(expansion.end)));
+
+        let mut firsts: Vec<Variable> = vec![];
+
+        for variable in variables {
+            if !firsts.iter().any(|first| first.addr == variable.addr) {
+                firsts.push(variable);
+            }
+        }
+
+        firsts.sort_by_key(|variable| variable.addr);
+
+        slots.sort_by(|a, b| a.addr.cmp(&b.addr).then_with(|| a.name.cmp(&b.name)));
+
+        SymbolMap { header_size, labels, expansions, variables: firsts, slots }
+    }
+}
+
+impl fmt::Display for SymbolMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.header_size == 1 { "" } else { "s" };
+
+        writeln!(f, "header: {} byte{plural}, code address 0 is file offset {:#06x}", self.header_size, self.header_size)?;
+
+        writeln!(f, "\nlabels:")?;
+        for (name, addr) in &self.labels {
+            writeln!(f, "    {addr:#06x}  {name}")?;
+        }
+
+        writeln!(f, "\nroutines:")?;
+        for Expansion { routine, start, end, site } in &self.expansions {
+            writeln!(f, "    {start:#06x}..{end:#06x}  {routine}  called at {site}")?;
+        }
+
+        writeln!(f, "\nvariables:")?;
+        for Variable { addr, kind, value, site } in &self.variables {
+            writeln!(f, "    ${addr:02x}  {kind:<5}  {value}  set at {site}")?;
+        }
+
+        writeln!(f, "\nnames:")?;
+        let width = self.slots.iter().map(|slot| slot.name.len()).max().unwrap_or(0);
+        for Slot { name, addr, len, pinned, site } in &self.slots {
+            let addrs = match len {
+                1 => format!("${addr:02x}"),
+                _ => format!("${addr:02x}-${:02x}", *addr as usize + len - 1),
+            };
+            let how = if *pinned { "pinned" } else { "allocated" };
+
+            writeln!(f, "    {addrs:<7}  {name:<width$}  {how} at {site}")?;
+        }
+
+        Ok(())
+    }
+}
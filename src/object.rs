@@ -0,0 +1,286 @@
+//! Relocatable object files, written by [`Assembler::assemble_object`] and combined into
+//! one program by [`link`](crate::link::link).
+//!
+//! An object holds the assembled header flags and code of one source file, the code
+//! address of every label it defines, a [`Relocation`] for every code address in its code,
+//! the routines it declares, and what the file's [symbol map](crate::map) and
+//! [debug info](crate::debug) need. Jumps to labels the file does not define are left as
+//! imports for the linker to fill in.
+//!
+//! Routines are inlined where they are called, so they cannot be linked as code. Instead
+//! an object carries the source it was assembled from, and [`Assembler::import`] reads
+//! the routines back out of it so other files can `call` them.
+//!
+//! On disk an object is the magic bytes `ASCO`, a format version byte, and then each field
+//! in order. Numbers are 8 bytes little-endian, like code addresses in bytecode, and
+//! strings and byte strings are prefixed by their length.
+//!
+//! [`Assembler::assemble_object`]: crate::Assembler::assemble_object
+//! [`Assembler::import`]: crate::Assembler::import
+
+use crate::codegen::Relocation;
+use crate::debug::{Entry, Frame, Location};
+use crate::error::AscError;
+use crate::map::{Expansion, Slot, Variable};
+
+const MAGIC: &[u8; 4] = b"ASCO";
+const VERSION: u8 = 4;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Object {
+    /// The name of the source file.
+    pub name: String,
+    /// The text of the source file, for reading its routines back.
+    pub source: String,
+    /// The header flags, without the zero byte that ends the header.
+    pub header: Vec<u8>,
+    /// Everything after the header, starting at code address 0.
+    pub code: Vec<u8>,
+    /// The code address of every label the file defines, sorted by name.
+    pub labels: Vec<(String, usize)>,
+    /// Every code address in `code`, with `at` counted from the start of `code`.
+    pub relocations: Vec<Relocation>,
+    /// The name of every routine the file declares, sorted.
+    pub routines: Vec<String>,
+    /// Every `call` in the file, at code addresses within `code`.
+    pub expansions: Vec<Expansion>,
+    /// Every variable set by the file, in order.
+    pub variables: Vec<Variable>,
+    /// Every variable name the file declares.
+    pub slots: Vec<Slot>,
+    /// Every instruction in `code`, with offsets counted from the start of `code`.
+    pub debug: Vec<Entry>,
+}
+
+impl Object {
+    /// The code address of `label`, if this object defines it.
+    pub fn label(&self, label: &str) -> Option<usize> {
+        self.labels.iter().find(|(name, _)| name == label).map(|&(_, addr)| addr)
+    }
+
+    /// The labels jumped to but not defined by this object, in order of first use.
+    pub fn imports(&self) -> Vec<&str> {
+        let mut imports = vec![];
+
+        for relocation in &self.relocations {
+            if let Some(label) = &relocation.label
+                && self.label(label).is_none()
+                && !imports.contains(&label.as_str())
+            {
+                imports.push(label.as_str());
+            }
+        }
+
+        imports
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+
+        out.0.push(VERSION);
+        out.str(&self.name);
+        out.str(&self.source);
+        out.bytes(&self.header);
+        out.bytes(&self.code);
+
+        out.num(self.labels.len());
+        for (name, addr) in &self.labels {
+            out.str(name);
+            out.num(*addr);
+        }
+
+        out.num(self.relocations.len());
+        for relocation in &self.relocations {
+            out.num(relocation.at);
+            out.str(relocation.label.as_deref().unwrap_or(""));
+        }
+
+        out.num(self.routines.len());
+        for routine in &self.routines {
+            out.str(routine);
+        }
+
+        out.num(self.expansions.len());
+        for expansion in &self.expansions {
+            out.str(&expansion.routine);
+            out.num(expansion.start);
+            out.num(expansion.end);
+            out.str(&expansion.site);
+        }
+
+        out.num(self.variables.len());
+        for variable in &self.variables {
+            out.0.push(variable.addr);
+            out.str(&variable.kind);
+            out.str(&variable.value);
+            out.str(&variable.site);
+        }
+
+        out.num(self.slots.len());
+        for slot in &self.slots {
+            out.str(&slot.name);
+            out.0.push(slot.addr);
+            out.num(slot.len);
+            out.0.push(slot.pinned as u8);
+            out.str(&slot.site);
+        }
+
+        out.num(self.debug.len());
+        for entry in &self.debug {
+            out.num(entry.offset);
+            out.num(entry.len);
+            out.location(&entry.location);
+
+            out.num(entry.expansion.len());
+            for frame in &entry.expansion {
+                out.str(&frame.what);
+                out.location(&frame.location);
+            }
+        }
+
+        out.0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Object, AscError> {
+        let Some(rest) = bytes.strip_prefix(MAGIC) else {
+            return Err(AscError::InvalidObject { reason: "it does not start with `ASCO`" })
+        };
+
+        let mut reader = Reader(rest);
+
+        if reader.take(1)? != [VERSION] {
+            return Err(AscError::InvalidObject { reason: "it was written by another version of the assembler" })
+        }
+
+        let mut object = Object {
+            name: reader.str()?,
+            source: reader.str()?,
+            header: reader.bytes()?.to_vec(),
+            code: reader.bytes()?.to_vec(),
+            ..Object::default()
+        };
+
+        for _ in 0..reader.num()? {
+            object.labels.push((reader.str()?, reader.num()?));
+        }
+
+        for _ in 0..reader.num()? {
+            let at = reader.num()?;
+            let label = Some(reader.str()?).filter(|label| !label.is_empty());
+
+            if at.checked_add(8).is_none_or(|end| end > object.code.len()) {
+                return Err(AscError::InvalidObject { reason: "a relocation points past the end of the code" })
+            }
+
+            object.relocations.push(Relocation { at, label });
+        }
+
+        for _ in 0..reader.num()? {
+            object.routines.push(reader.str()?);
+        }
+
+        for _ in 0..reader.num()? {
+            object.expansions.push(Expansion {
+                routine: reader.str()?,
+                start: reader.num()?,
+                end: reader.num()?,
+                site: reader.str()?,
+            });
+        }
+
+        for _ in 0..reader.num()? {
+            object.variables.push(Variable {
+                addr: reader.take(1)?[0],
+                kind: reader.str()?,
+                value: reader.str()?,
+                site: reader.str()?,
+            });
+        }
+
+        for _ in 0..reader.num()? {
+            object.slots.push(Slot {
+                name: reader.str()?,
+                addr: reader.take(1)?[0],
+                len: reader.num()?,
+                pinned: reader.take(1)?[0] != 0,
+                site: reader.str()?,
+            });
+        }
+
+        for _ in 0..reader.num()? {
+            let mut entry = Entry { offset: reader.num()?, len: reader.num()?, location: reader.location()?, expansion: vec![] };
+
+            for _ in 0..reader.num()? {
+                entry.expansion.push(Frame { what: reader.str()?, location: reader.location()? });
+            }
+
+            object.debug.push(entry);
+        }
+
+        if !reader.0.is_empty() {
+            return Err(AscError::InvalidObject { reason: "it has bytes past its end" })
+        }
+
+        Ok(object)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn num(&mut self, num: usize) {
+        self.0.extend((num as u64).to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.num(bytes.len());
+        self.0.extend(bytes);
+    }
+
+    fn str(&mut self, str: &str) {
+        self.bytes(str.as_bytes());
+    }
+
+    fn location(&mut self, location: &Location) {
+        self.str(&location.file);
+        self.num(location.line);
+        self.num(location.col);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AscError> {
+        if len > self.0.len() {
+            return Err(AscError::InvalidObject { reason: "it is cut short" })
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn num(&mut self) -> Result<usize, AscError> {
+        let bytes = self.take(8)?.try_into().unwrap();
+
+        usize::try_from(u64::from_le_bytes(bytes))
+            .map_err(|_| AscError::InvalidObject { reason: "it holds a number too big for this machine" })
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], AscError> {
+        let len = self.num()?;
+
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<String, AscError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| AscError::InvalidObject { reason: "it holds a string that is not UTF-8" })
+    }
+
+    fn location(&mut self) -> Result<Location, AscError> {
+        Ok(Location { file: self.str()?, line: self.num()?, col: self.num()? })
+    }
+}
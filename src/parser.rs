@@ -0,0 +1,380 @@
+//! Builds the syntax tree out of the flat token stream, in three steps.
+//!
+//! First the uses of [constants](crate::constants) are marked. Then the `{`, `}`,
+//! `[` and `]` tokens left by the tokenizer are paired up, nesting the tokens between each
+//! pair into a [`TokenKind::Block`] or [`TokenKind::Array`] whose span covers both
+//! delimiters. Finally statements are read off the nested tokens, checking each
+//! instruction's operands against the [instruction table](crate::isa), to give a
+//! [`Program`]. A constant is read as its value where the table expects a number, byte,
+//! float or value, and as a name anywhere else.
+
+use std::vec::IntoIter;
+
+use crate::ast::{Block, Decl, Instr, Operand, OperandKind, Program, Stmt};
+use crate::constants;
+use crate::diagnostics::{Diagnostic, Diagnostics, did_you_mean};
+use crate::error::AscError;
+use crate::isa::{self, Instruction, OperandType};
+use crate::span::Span;
+use crate::tokens::{Token, TokenKind};
+
+fn is_mnemonic(name: &str) -> bool {
+    isa::lookup(name).is_some()
+}
+
+/// What `token` is as an operand of type `ty`: the value of a constant where a value can
+/// go, or the constant's name.
+fn resolve_constant(ty: OperandType, token: TokenKind) -> TokenKind {
+    match token {
+        TokenKind::Constant { value, .. }
+            if matches!(ty, OperandType::Byte | OperandType::Bytes | OperandType::Num | OperandType::Float | OperandType::Value) =>
+        {
+            *value
+        }
+        TokenKind::Constant { name, .. } => TokenKind::Ident(name),
+        token => token,
+    }
+}
+
+/// Whether `token` can be used as an operand of type `ty`.
+fn accepts(ty: OperandType, token: &TokenKind) -> bool {
+    matches!(
+        (ty, token),
+        (OperandType::Var | OperandType::Out, TokenKind::Var(_) | TokenKind::Ident(_))
+            | (OperandType::Byte | OperandType::Bytes, TokenKind::Byte(_))
+            | (OperandType::Byte, TokenKind::Num(_))
+            | (OperandType::Num, TokenKind::Num(_))
+            | (OperandType::Float, TokenKind::Float(_))
+            | (OperandType::Value, TokenKind::Str(_) | TokenKind::Num(_) | TokenKind::Float(_) | TokenKind::Array(_))
+            | (OperandType::Label | OperandType::Name, TokenKind::Ident(_))
+            | (OperandType::Block, TokenKind::Block(_))
+            | (OperandType::Array | OperandType::Value, TokenKind::NamedArray(_))
+            | (OperandType::Array, TokenKind::Array(_))
+    )
+}
+
+/// Parses `tokens` into a [`Program`].
+///
+/// Problems are pushed onto `diagnostics` and the broken statements left out of the tree,
+/// so parsing always reaches the end of the input.
+pub fn parse(tokens: Vec<Token>, diagnostics: &mut Diagnostics) -> Program {
+    let tokens = constants::substitute(tokens, diagnostics);
+    let tokens = Nesting { tokens: tokens.into_iter(), diagnostics }.sequence(None).0;
+
+    Program { stmts: Parser { tokens: tokens.into_iter(), diagnostics }.statements() }
+}
+
+/// Pairs up delimiters.
+struct Nesting<'a> {
+    tokens: IntoIter<Token>,
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl Nesting<'_> {
+    /// Nests tokens up to `close`, returning them along with the span of the `close`, or
+    /// up to the end of the input, returning no span.
+    fn sequence(&mut self, close: Option<&TokenKind>) -> (Vec<Token>, Option<Span>) {
+        let mut out = vec![];
+
+        while let Some(token) = self.tokens.next() {
+            match token.kind {
+                TokenKind::OpenBrace => out.push(self.block(token.span)),
+                TokenKind::OpenBracket => out.push(self.array(token.span)),
+                ref kind if Some(kind) == close => return (out, Some(token.span)),
+                TokenKind::CloseBrace | TokenKind::CloseBracket => {
+                    self.diagnostics.push(Diagnostic::new(AscError::UnmatchedDelimiter { found: token.kind }).at(token.span));
+                }
+                _ => out.push(token),
+            }
+        }
+
+        (out, None)
+    }
+
+    /// Nests the rest of a block opened at `open`.
+    fn block(&mut self, open: Span) -> Token {
+        let (body, close) = self.sequence(Some(&TokenKind::CloseBrace));
+
+        let Some(close) = close else {
+            self.diagnostics.push(Diagnostic::new(AscError::UnexpectedEof { context: "block" }).at(open));
+            return Token::new(TokenKind::Error, open)
+        };
+
+        Token::new(TokenKind::Block(body), open.to(close))
+    }
+
+    /// Nests the rest of an array opened at `open`. Arrays hold exactly 8 bytes, variable
+    /// addresses or variable names; numbers that fit in a byte are accepted too.
+    fn array(&mut self, open: Span) -> Token {
+        let (elements, close) = self.sequence(Some(&TokenKind::CloseBracket));
+
+        let Some(close) = close else {
+            self.diagnostics.push(Diagnostic::new(AscError::UnexpectedEof { context: "array" }).at(open));
+            return Token::new(TokenKind::Error, open)
+        };
+
+        let span = open.to(close);
+
+        if elements.len() != 8 {
+            self.diagnostics.push(Diagnostic::new(AscError::ArrayLength { len: elements.len() }).at(span));
+            return Token::new(TokenKind::Error, span)
+        }
+
+        let mut arr = [0u8; 8];
+        let mut named = vec![];
+
+        for (i, element) in elements.into_iter().enumerate() {
+            // Arrays hold values, so a constant in one is its value.
+            let kind = match element.kind {
+                TokenKind::Constant { value, .. } => *value,
+                kind => kind,
+            };
+
+            let byte = match kind {
+                TokenKind::Ident(name) => {
+                    named.push(Token::new(TokenKind::Ident(name), element.span));
+                    continue
+                }
+                TokenKind::Byte(byte) | TokenKind::Var(byte) => Ok(byte),
+                TokenKind::Num(value) => AscError::byte_range(value),
+                // Already reported when it failed to tokenize.
+                TokenKind::Error => return Token::new(TokenKind::Error, span),
+                found => Err(AscError::InvalidArrayElement { found }),
+            };
+
+            match byte {
+                Ok(byte) => {
+                    arr[i] = byte;
+                    named.push(Token::new(TokenKind::Byte(byte), element.span));
+                }
+                Err(err) => {
+                    self.diagnostics.push(Diagnostic::new(err).at(element.span));
+                    return Token::new(TokenKind::Error, span)
+                }
+            }
+        }
+
+        if named.iter().any(|element| matches!(element.kind, TokenKind::Ident(_))) {
+            return Token::new(TokenKind::NamedArray(named), span)
+        }
+
+        Token::new(TokenKind::Array(arr), span)
+    }
+}
+
+/// Reads statements off nested tokens.
+struct Parser<'a> {
+    tokens: IntoIter<Token>,
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl Parser<'_> {
+    /// The token `n` tokens ahead, without moving past it.
+    fn peek(&self, n: usize) -> Option<&Token> {
+        self.tokens.as_slice().get(n)
+    }
+
+    /// Parses every statement left, reporting each broken one and carrying on after it.
+    fn statements(&mut self) -> Vec<Stmt> {
+        let mut stmts = vec![];
+
+        while let Some(token) = self.tokens.next() {
+            let line = token.span.line;
+
+            match self.statement(token) {
+                Ok(Some(stmt)) => stmts.push(stmt),
+                Ok(None) => {}
+                Err(diagnostic) => {
+                    let line = diagnostic.span.map_or(line, |span| span.line);
+
+                    self.diagnostics.report(diagnostic);
+
+                    self.recover(line);
+                }
+            }
+        }
+
+        stmts
+    }
+
+    /// Parses the statement starting with `token`. A lone `;` is no statement at all.
+    fn statement(&mut self, token: Token) -> Result<Option<Stmt>, Diagnostic> {
+        match token.kind {
+            TokenKind::Ident(name) if self.declares(&name) => Ok(Some(Stmt::Decl(self.decl(name, token.span)?))),
+            TokenKind::Ident(name) => Ok(Some(Stmt::Instr(self.instr(name, token.span)?))),
+            TokenKind::Label(name) => Ok(Some(Stmt::Label { name, span: token.span })),
+            TokenKind::EndL => Ok(None),
+            found => Err(Diagnostic::new(AscError::UnexpectedToken { found }).at(token.span)),
+        }
+    }
+
+    /// Whether the `var` or `let` `keyword` starts a declaration, `var name @` or `var name =`,
+    /// rather than setting a variable.
+    fn declares(&self, keyword: &str) -> bool {
+        matches!(keyword, "var" | "let")
+            && matches!(self.peek(0), Some(Token { kind: TokenKind::Ident(_), .. }))
+            && matches!(self.peek(1), Some(Token { kind: TokenKind::At | TokenKind::Assign, .. }))
+    }
+
+    /// Parses the rest of a declaration started by the `var` or `let` `keyword` at `span`.
+    fn decl(&mut self, keyword: String, span: Span) -> Result<Decl, Diagnostic> {
+        let Some(instruction) = isa::lookup(&keyword) else {
+            unreachable!("`{keyword}` is in the table")
+        };
+
+        let Some(Token { kind: TokenKind::Ident(name), span: name_span }) = self.tokens.next() else {
+            unreachable!("checked by `declares`")
+        };
+
+        let target = Operand { kind: OperandKind::Name(name.clone()), span: name_span };
+        let mut instr = Instr { name: keyword, span, form: &instruction.forms[0], operands: vec![target] };
+        let mut decl = Decl { name, span: name_span, addr: None, init: None };
+
+        if let Some(Token { kind: TokenKind::At, .. }) = self.peek(0) {
+            self.tokens.next();
+
+            let Operand { kind, span } = self.operand(&instr, instruction, OperandType::Out)?;
+
+            // Only pinned to an address, not to another name.
+            decl.addr = Some(match kind {
+                OperandKind::Var(addr) => addr,
+                OperandKind::Name(found) => {
+                    let error = AscError::InvalidOperand {
+                        inst: instr.name,
+                        expected: instruction.signature(),
+                        found: TokenKind::Ident(found),
+                    };
+                    return Err(Diagnostic::new(error).at(span))
+                }
+                _ => unreachable!("a `$var` operand is a `Var` or a `Name`"),
+            });
+        }
+
+        if let Some(Token { kind: TokenKind::Assign, .. }) = self.peek(0) {
+            self.tokens.next();
+
+            let value = self.operand(&instr, instruction, OperandType::Value)?;
+
+            instr.operands.push(value);
+            decl.init = Some(instr);
+        }
+
+        Ok(decl)
+    }
+
+    fn instr(&mut self, name: String, span: Span) -> Result<Instr, Diagnostic> {
+        let Some(instruction) = isa::lookup(&name) else {
+            let suggestion = did_you_mean(&name, span, isa::mnemonics());
+
+            return Err(Diagnostic::new(AscError::UnknownInstruction { name }).at(span).suggest(suggestion))
+        };
+
+        let find = |token: &TokenKind| {
+            instruction.forms.iter().find(|form| form.operands.first().is_some_and(|&ty| accepts(ty, token)))
+        };
+
+        // A constant picks a form for its value before one for its name.
+        let form = match self.peek(0).map(|token| &token.kind) {
+            Some(TokenKind::Constant { name, value }) => find(value).or_else(|| find(&TokenKind::Ident(name.clone()))),
+            Some(token) => find(token),
+            None => None,
+        };
+        let form = form.unwrap_or(&instruction.forms[0]);
+
+        let mut instr = Instr { name, span, form, operands: vec![] };
+
+        for &ty in form.operands {
+            if ty == OperandType::Bytes {
+                while let Some(token) = self.peek(0)
+                    && let TokenKind::Byte(byte) = resolve_constant(ty, token.kind.clone())
+                {
+                    instr.operands.push(Operand { kind: OperandKind::Byte(byte), span: token.span });
+                    self.tokens.next();
+                }
+                continue
+            }
+
+            let operand = self.operand(&instr, instruction, ty)?;
+
+            instr.operands.push(operand);
+        }
+
+        Ok(instr)
+    }
+
+    /// Reads the next operand of `instr`, which should be a `ty`. The operand is missing if
+    /// the statement ends first: at the end of the input, at a `;`, or at a mnemonic on a
+    /// later line.
+    fn operand(&mut self, instr: &Instr, instruction: &Instruction, ty: OperandType) -> Result<Operand, Diagnostic> {
+        let missing = match self.peek(0) {
+            None => true,
+            Some(Token { kind: TokenKind::EndL, .. }) => true,
+            Some(Token { kind: TokenKind::Ident(name), span }) => span.line > instr.span.line && is_mnemonic(name),
+            Some(_) => false,
+        };
+
+        if missing {
+            let error = AscError::MissingOperand { inst: instr.name.clone(), expected: instruction.signature() };
+            return Err(Diagnostic::new(error).at(instr.span))
+        }
+
+        let Token { kind: token, span } = self.tokens.next().unwrap();
+
+        let kind = match (ty, resolve_constant(ty, token)) {
+            (OperandType::Var | OperandType::Out, TokenKind::Var(addr)) => OperandKind::Var(addr),
+            (OperandType::Var | OperandType::Out, TokenKind::Ident(name)) => OperandKind::Name(name),
+            (OperandType::Byte, TokenKind::Byte(byte)) => OperandKind::Byte(byte),
+            (OperandType::Byte, TokenKind::Num(num)) => {
+                OperandKind::Byte(AscError::byte_range(num).map_err(|err| Diagnostic::new(err).at(span))?)
+            }
+            (OperandType::Num | OperandType::Value, TokenKind::Num(num)) => OperandKind::Num(num),
+            (OperandType::Float | OperandType::Value, TokenKind::Float(float)) => OperandKind::Float(float),
+            (OperandType::Value, TokenKind::Str(str)) => OperandKind::Str(str),
+            (OperandType::Array | OperandType::Value, TokenKind::Array(arr)) => OperandKind::Array(arr),
+            (OperandType::Array | OperandType::Value, TokenKind::NamedArray(elements)) => {
+                let elements = elements.into_iter()
+                    .map(|element| match element.kind {
+                        TokenKind::Ident(name) => Operand { kind: OperandKind::Name(name), span: element.span },
+                        TokenKind::Byte(byte) => Operand { kind: OperandKind::Byte(byte), span: element.span },
+                        _ => unreachable!("checked when nesting"),
+                    })
+                    .collect();
+
+                OperandKind::NamedArray(elements)
+            }
+            (OperandType::Label | OperandType::Name, TokenKind::Ident(name)) => OperandKind::Name(name),
+            (OperandType::Block, TokenKind::Block(tokens)) => OperandKind::Block(self.block(tokens, span)),
+            (_, found) => {
+                let error = AscError::InvalidOperand { inst: instr.name.clone(), expected: instruction.signature(), found };
+                return Err(Diagnostic::new(error).at(span))
+            }
+        };
+
+        Ok(Operand { kind, span })
+    }
+
+    fn block(&mut self, tokens: Vec<Token>, span: Span) -> Block {
+        let stmts = Parser { tokens: tokens.into_iter(), diagnostics: self.diagnostics }.statements();
+
+        Block { stmts, span }
+    }
+
+    /// Skips what is left of a broken statement whose error was on `line`: everything up
+    /// to and including the next `;`, or up to the next line, label or known mnemonic.
+    fn recover(&mut self, line: usize) {
+        while let Some(token) = self.peek(0) {
+            match &token.kind {
+                TokenKind::EndL => {
+                    self.tokens.next();
+                    return
+                }
+                TokenKind::Ident(name) if is_mnemonic(name) => return,
+                TokenKind::Label(_) => return,
+                _ if token.span.line > line => return,
+                _ => {
+                    self.tokens.next();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Human-readable diagnostics in the style of rustc:
+//!
+//! ```text
+//! error[E0102]: Unexpected token `+01` in pix
+//!  --> main.asc:2:11
+//!   |
+//! 2 |     pix $00 +01 +02
+//!   |             ^^^
+//!   |
+//!   = help: pix expects `$var $var +byte`
+//! ```
+
+use std::fmt::Write;
+
+use crate::diagnostics::{Diagnostic, DiagnosticKind, Severity};
+use crate::span::{SourceMap, Span};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Wraps text in ANSI escapes when colour is enabled.
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+pub fn render(diagnostic: &Diagnostic, sources: &SourceMap, color: bool) -> String {
+    let style = Style { color };
+    let mut out = String::new();
+
+    let accent = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+
+    let _ = write!(
+        out,
+        "{}{}",
+        style.paint(accent, &format!("{}[{}]", diagnostic.severity, diagnostic.code())),
+        style.paint(BOLD, &format!(": {}", diagnostic.message())),
+    );
+
+    let mut notes = vec![];
+
+    if let DiagnosticKind::Lint(warning, set_by) = &*diagnostic.kind {
+        let name = warning.lint().name();
+
+        notes.push(match diagnostic.severity {
+            Severity::Error => format!("note: this lint was denied by `-D {set_by}`"),
+            Severity::Warning => format!("note: silence this warning with `-A {name}`"),
+        });
+    }
+
+    if let Some(help) = diagnostic.help() {
+        notes.push(format!("help: {help}"));
+    }
+
+    if let Some(suggestion) = &diagnostic.suggestion {
+        notes.push(format!("help: {}", suggestion.message));
+    }
+
+    let Some(span) = diagnostic.span else {
+        for note in notes {
+            let _ = write!(out, "\n{} {note}", style.paint(BLUE, "="));
+        }
+        return out
+    };
+
+    let line_number = span.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let bar = style.paint(BLUE, "|");
+
+    let _ = write!(
+        out,
+        "\n{gutter}{} {}:{}:{}",
+        style.paint(BLUE, "-->"),
+        sources.name(span.file),
+        span.line,
+        span.col,
+    );
+
+    if let Some((line, padding, width)) = snippet(sources, span) {
+        let _ = write!(out, "\n{gutter} {bar}");
+        let _ = write!(out, "\n{} {bar} {line}", style.paint(BLUE, &line_number));
+        let _ = write!(out, "\n{gutter} {bar} {padding}{}", style.paint(accent, &"^".repeat(width)));
+    }
+
+    if !notes.is_empty() {
+        let _ = write!(out, "\n{gutter} {bar}");
+    }
+
+    for note in notes {
+        let _ = write!(out, "\n{gutter} {} {note}", style.paint(BLUE, "="));
+    }
+
+    out
+}
+
+/// The source line `span` starts on, the whitespace that lines a caret up under the start
+/// of the span, and how many carets to draw. Spans over several lines are underlined up
+/// to the end of their first line.
+fn snippet(sources: &SourceMap, span: Span) -> Option<(&str, String, usize)> {
+    let text = &sources.get(span.file)?.text;
+
+    let line = text.lines().nth(span.line.checked_sub(1)?)?;
+
+    let line_start = line.as_ptr() as usize - text.as_ptr() as usize;
+    let start = span.start.checked_sub(line_start)?.min(line.len());
+    let end = span.end.saturating_sub(line_start).clamp(start, line.len());
+
+    // Keep tabs so the caret lines up however wide the terminal renders them.
+    let padding = line[..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let width = line[start..end].chars().count().max(1);
+
+    Some((line, padding, width))
+}
@@ -0,0 +1,224 @@
+//! Gives every variable name an address, replacing each name used as a `$var` or in an
+//! array with the address it stands for, before the tree is compiled.
+//!
+//! A name is declared once, by `var name = value`, `var name @ $addr` or both, and can be
+//! used anywhere in the file. A name without an `@` gets the lowest address that no `$var`
+//! in the file, no other name and no name of an imported object uses, or the lowest run
+//! of them for a string longer than one variable. Names are allocated file by file, so
+//! names used by several linked files should be pinned with `@`.
+//!
+//! Routines imported from an object file use the names of the file that declared them,
+//! which are resolved when the object is imported.
+
+use std::collections::HashMap;
+
+use crate::Assembler;
+use crate::ast::{Decl, Instr, Operand, OperandKind, Stmt};
+use crate::diagnostics::{Diagnostic, Diagnostics, did_you_mean};
+use crate::error::AscError;
+use crate::isa::OperandType;
+use crate::map::Slot;
+use crate::span::Span;
+
+use OperandKind::*;
+
+/// Calls `f` on every statement of `stmts`, and of the blocks inside them.
+fn walk(stmts: &mut [Stmt], f: &mut impl FnMut(&mut Stmt)) {
+    for stmt in stmts {
+        f(stmt);
+
+        if let Stmt::Instr(instr) = stmt {
+            for operand in &mut instr.operands {
+                if let OperandKind::Block(block) = &mut operand.kind {
+                    walk(&mut block.stmts, f);
+                }
+            }
+        }
+    }
+}
+
+/// How many variables a `var` or `let` of `value` sets: one for every 8 bytes of a string.
+fn slots(value: &Operand) -> usize {
+    match &value.kind {
+        Str(str) => str.len().div_ceil(8).max(1),
+        _ => 1,
+    }
+}
+
+/// The `len` addresses from `addr`, stopping at `$ff`.
+fn run(addr: u8, len: usize) -> impl Iterator<Item = u8> {
+    (addr as usize..(addr as usize + len).min(0x100)).map(|addr| addr as u8)
+}
+
+/// Every address `instr` uses with a `$var`, including those a string runs on into.
+fn addresses(instr: &Instr) -> Vec<u8> {
+    let mut addrs = vec![];
+
+    for (operand, ty) in instr.operands.iter().zip(instr.form.operands) {
+        match (&operand.kind, ty) {
+            (Var(addr), _) => addrs.push(*addr),
+            (Array(arr), OperandType::Array) => addrs.extend(arr),
+            (NamedArray(elements), OperandType::Array) => {
+                addrs.extend(elements.iter().filter_map(|element| match element.kind {
+                    Byte(addr) => Some(addr),
+                    _ => None,
+                }))
+            }
+            _ => {}
+        }
+    }
+
+    if let ("var" | "let", [Operand { kind: Var(addr), .. }, value]) = (instr.name.as_str(), &instr.operands[..]) {
+        addrs.extend(run(*addr, slots(value)).skip(1));
+    }
+
+    addrs
+}
+
+/// The names of the routines `stmts` calls, added to `names` if they are not in it yet.
+fn calls(stmts: &mut [Stmt], names: &mut Vec<String>) {
+    walk(stmts, &mut |stmt| {
+        if let Stmt::Instr(instr) = stmt
+            && let ("call", [Operand { kind: Name(name), .. }]) = (instr.name.as_str(), &instr.operands[..])
+            && !names.contains(name)
+        {
+            names.push(name.clone());
+        }
+    });
+}
+
+/// Replaces the variable names used in `stmts` with their addresses in `names`.
+pub(crate) fn substitute(stmts: &mut [Stmt], names: &HashMap<String, u8>, diagnostics: &mut Diagnostics) {
+    walk(stmts, &mut |stmt| {
+        if let Stmt::Instr(instr) | Stmt::Decl(Decl { init: Some(instr), .. }) = stmt {
+            substitute_instr(instr, names, diagnostics);
+        }
+    });
+}
+
+/// Replaces the variable names in the operands of `instr` with their addresses.
+fn substitute_instr(instr: &mut Instr, names: &HashMap<String, u8>, diagnostics: &mut Diagnostics) {
+    let form = instr.form;
+
+    for (operand, ty) in instr.operands.iter_mut().zip(form.operands) {
+        match &operand.kind {
+            Name(name) if matches!(ty, OperandType::Var | OperandType::Out) => {
+                operand.kind = Var(address(name, operand.span, names, diagnostics));
+            }
+            NamedArray(elements) => {
+                let mut arr = [0; 8];
+
+                for (byte, element) in arr.iter_mut().zip(elements) {
+                    *byte = match &element.kind {
+                        Name(name) => address(name, element.span, names, diagnostics),
+                        Byte(byte) => *byte,
+                        _ => unreachable!("checked by the parser"),
+                    };
+                }
+
+                operand.kind = Array(arr);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The address of the variable `name` used at `span`. An unknown name is reported and
+/// stands for `$00`, so the rest of the file can still be checked.
+fn address(name: &str, span: Span, names: &HashMap<String, u8>, diagnostics: &mut Diagnostics) -> u8 {
+    if let Some(&addr) = names.get(name) {
+        return addr
+    }
+
+    let suggestion = did_you_mean(name, span, names.keys().map(String::as_str));
+    diagnostics.report(Diagnostic::new(AscError::UnknownVariable { name: name.to_string() }).at(span).suggest(suggestion));
+
+    0
+}
+
+impl Assembler {
+    /// Gives every variable name declared in `stmts` an address, and replaces the names
+    /// used in `stmts` with their addresses.
+    pub(crate) fn resolve(&mut self, stmts: &mut [Stmt]) {
+        let mut used = [false; 0x100];
+
+        for slot in &self.library_slots {
+            for addr in run(slot.addr, slot.len) {
+                used[addr as usize] = true;
+            }
+        }
+
+        let mut mark = |stmt: &mut Stmt| match stmt {
+            Stmt::Instr(instr) => {
+                for addr in addresses(instr) {
+                    used[addr as usize] = true;
+                }
+            }
+            Stmt::Decl(Decl { addr: Some(addr), init, .. }) => {
+                let len = init.as_ref().map_or(1, |init| slots(&init.operands[1]));
+
+                for addr in run(*addr, len) {
+                    used[addr as usize] = true;
+                }
+            }
+            _ => {}
+        };
+
+        walk(stmts, &mut mark);
+
+        // Library routines already use the addresses of their own file's names, which are
+        // kept free as long as this file calls them.
+        let mut called = vec![];
+        calls(stmts, &mut called);
+
+        let mut i = 0;
+        while let Some(name) = called.get(i).cloned() {
+            if let Some(block) = self.library.get(&name) {
+                let mut block = block.clone();
+                walk(&mut block.stmts, &mut mark);
+                calls(&mut block.stmts, &mut called);
+            }
+            i += 1;
+        }
+
+        let mut names: HashMap<String, u8> = HashMap::new();
+
+        walk(stmts, &mut |stmt| {
+            let Stmt::Decl(decl) = stmt else { return };
+
+            if names.contains_key(&decl.name) {
+                let error = AscError::DuplicateVariable { name: decl.name.clone() };
+                return self.report(Diagnostic::new(error).at(decl.span))
+            }
+
+            let len = decl.init.as_ref().map_or(1, |init| slots(&init.operands[1]));
+
+            let addr = match decl.addr {
+                Some(addr) => addr,
+                None => {
+                    let free = (0..0x100usize)
+                        .find(|&start| start + len <= 0x100 && used[start..start + len].iter().all(|used| !used));
+
+                    let Some(start) = free else {
+                        // Still a name, so its uses are not reported as unknown too.
+                        names.insert(decl.name.clone(), 0);
+                        decl.init = None;
+
+                        let error = AscError::OutOfVariables { name: decl.name.clone(), slots: len };
+                        return self.report(Diagnostic::new(error).at(decl.span))
+                    };
+
+                    used[start..start + len].fill(true);
+                    start as u8
+                }
+            };
+
+            names.insert(decl.name.clone(), addr);
+
+            let site = self.site(decl.span);
+            self.slots.push(Slot { name: decl.name.clone(), addr, len, pinned: decl.addr.is_some(), site });
+        });
+
+        substitute(stmts, &names, &mut self.diagnostics);
+    }
+}
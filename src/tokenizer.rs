@@ -1,192 +1,456 @@
-use crate::tokens::Token;
+//! Turns source text into [`Token`]s.
+//!
+//! The lexical grammar, where `hex` is `[0-9A-Fa-f]` and whitespace separates tokens
+//! but is otherwise ignored:
+//!
+//! ```text
+//! ident    = [A-Za-z_] [A-Za-z0-9_]*        mnemonics, label and routine names
+//! label    = "!" ident                      a jump target
+//! var      = "$" hex+                       a variable address, $00 to $ff
+//! byte     = "+" hex hex                    a constant byte
+//! number   = "-"? digits                    a constant number, or a float if decimal
+//!                                           with a fraction or exponent
+//!          | "@" hex [hex_]*                the original hexadecimal form
+//!          | "'" (char | escape) "'"        a character's byte
+//! escape   = "\\" [nt0\\"'] | "\\x" hex hex
+//! digits   = [0-9] [0-9_]* ("." [0-9_]*)? ([eE] [+-]? [0-9_]+)?
+//!          | "0x" hex [hex_]* | "0b" [01] [01_]* | "0o" [0-7] [0-7_]*
+//! float    = "#" "-"? [0-9] [0-9_.]* ([eE] [+-]? [0-9_]+)?
+//!                                           a constant float
+//! string   = '"' (char | escape)* '"'       split over as many variables as it needs
+//! delim    = "{" | "}" | "[" | "]"          paired up by the parser
+//! end      = ";"                            ends a statement early
+//! at       = "@"                            pins a variable name, when no hex follows
+//! assign   = "="                            gives a variable name or constant its value
+//! comment  = "//" [^\n]*                    skipped, as are the two forms below
+//!          | "/*" (comment | any)* "*/"     these nest
+//!          | "/" [^/]* "/"                  the original form
+//! ```
+//!
+//! Characters in strings must be in the console's character set, see [`charset`].
+//!
+//! Tokens end at the first character that cannot continue them, so `noop;` is the
+//! identifier `noop` followed by `;`.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::charset;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::AscError;
+use crate::span::{FileId, Span};
+use crate::tokens::{Token, TokenKind};
+
+/// Position of the next character a [`Cursor`] will read.
+#[derive(Debug, Clone, Copy)]
+struct Pos {
+    offset: usize,
+    line: usize,
+    col: usize,
+}
+
+/// Walks the characters of a file, keeping track of where it is.
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: Pos,
+    file: FileId,
+}
+
+impl Cursor<'_> {
+    fn next(&mut self) -> Option<char> {
+        let chr = self.chars.next()?;
+
+        self.pos.offset += chr.len_utf8();
+
+        if chr == '\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+
+        Some(chr)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
 
-pub fn tokenize(file: String) -> Vec<Token> {
-    let mut file = file.chars().peekable();
+    /// The span from `start` up to the current position.
+    fn span_from(&self, start: Pos) -> Span {
+        Span {
+            file: self.file,
+            start: start.offset,
+            end: self.pos.offset,
+            line: start.line,
+            col: start.col,
+        }
+    }
+}
+
+/// Tokenizes the source file `id` into a flat stream; see [`crate::parser`] for nesting
+/// blocks and arrays.
+///
+/// Problems are pushed onto `diagnostics` and the offending text is replaced by a
+/// [`TokenKind::Error`], so tokenizing always reaches the end of the file.
+pub fn tokenize(file: &str, id: FileId, diagnostics: &mut Diagnostics) -> Vec<Token> {
+    let mut file = Cursor { chars: file.chars().peekable(), pos: Pos { offset: 0, line: 1, col: 1 }, file: id };
 
     let mut out = vec![];
 
-    let mut byte = 0;
+    'tokens: loop {
+        let start = file.pos;
+
+        let Some(chr) = file.next() else {
+            break
+        };
 
-    while let Some(chr) = file.next() {
         match chr {
-            '/' => {
-                while Some('/') != file.next() {
-                    byte += 1
+            '/' => match file.peek() {
+                Some('/') => {
+                    while let Some(chr) = file.next() && chr != '\n' {}
                 }
-                
-            }
-            'a'..='z' => {
-                let mut string = String::from(chr);
+                Some('*') => {
+                    file.next();
+
+                    let mut depth = 1;
+
+                    while depth != 0 {
+                        match file.next() {
+                            Some('/') if file.peek() == Some('*') => {
+                                file.next();
+                                depth += 1;
+                            }
+                            Some('*') if file.peek() == Some('/') => {
+                                file.next();
+                                depth -= 1;
+                            }
+                            Some(_) => {}
+                            None => {
+                                let error = AscError::UnterminatedComment { line: start.line, close: "*/" };
+                                diagnostics.push(Diagnostic::new(error).at(opener(&file, start, 2)));
+                                break 'tokens
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    // The original `/ ... /` form.
+                    while file.next() != Some('/') {
+                        if file.peek().is_none() {
+                            let error = AscError::UnterminatedComment { line: start.line, close: "/" };
+                            diagnostics.push(Diagnostic::new(error).at(opener(&file, start, 1)));
+                            break 'tokens
+                        }
+                    }
+                }
+            },
+            chr if is_ident_start(chr) => {
+                let mut ident = String::from(chr);
 
-                while let Some(chr @ 'a'..='z') = file.next() {
-                    string.push(chr);
-                    byte += 1;
+                while let Some(chr) = file.peek() && is_ident_continue(chr) {
+                    ident.push(file.next().unwrap());
                 }
 
-                out.push(Token::Ident(string))
+                out.push(Token::new(TokenKind::Ident(ident), file.span_from(start)))
             }
             '$' => {
                 let mut hex = String::new();
 
                 while let Some('a'..='f' | 'A'..='F' | '0'..='9') = file.peek() {
                     hex.push(file.next().unwrap());
-                    byte += 1;
                 }
 
-                let num = i64::from_str_radix(&hex, 16).unwrap();
+                let span = file.span_from(start);
+
+                let Ok(num) = i64::from_str_radix(&hex, 16) else {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "var accessor", text: format!("${hex}") }, span);
+                    continue
+                };
 
                 if num > 255 {
-                    panic!("Var accessor value out of bounds (Value was {num:x}, max is {:x}) at character {byte}", 255)
+                    error(&mut out, diagnostics, AscError::VarOutOfBounds { value: num }, span);
+                    continue
                 }
 
-                out.push(Token::Var(num as u8))
+                out.push(Token::new(TokenKind::Var(num as u8), span))
             }
 
+            // A lone `@` pins a variable name to an address.
+            '@' if !matches!(file.peek(), Some('a'..='f' | 'A'..='F' | '0'..='9' | '_')) => {
+                out.push(Token::new(TokenKind::At, file.span_from(start)))
+            }
             '@' => {
                 let mut hex = String::new();
 
-                while let Some('a'..='f' | 'A'..='F' | '0'..='9') = file.peek() {
+                while let Some('a'..='f' | 'A'..='F' | '0'..='9' | '_') = file.peek() {
                     hex.push(file.next().unwrap());
-                    byte += 1;
                 }
 
-                out.push(Token::Num(i64::from_str_radix(&hex, 16).unwrap()))
+                let span = file.span_from(start);
+
+                let Some(num) = integer(&hex, 16) else {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "hexadecimal number", text: format!("@{hex}") }, span);
+                    continue
+                };
+
+                out.push(Token::new(TokenKind::Num(num), span))
             }
             '#' => {
                 let mut float = String::new();
 
-                while let Some('0'..='9' | '.') = file.peek() {
+                if file.peek() == Some('-') {
                     float.push(file.next().unwrap());
-                    byte += 1;
                 }
 
-                out.push(Token::Float(float.parse().unwrap()))
+                while let Some(chr) = file.peek() && continues_number(&float, chr) {
+                    float.push(file.next().unwrap());
+                }
+
+                let span = file.span_from(start);
+
+                let Some(float) = self::float(&float) else {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "float", text: format!("#{float}") }, span);
+                    continue
+                };
+
+                out.push(Token::new(TokenKind::Float(float), span))
             }
             '+' => {
                 let mut val = String::new();
 
                 for _ in 0..2 {
-                    val.push(
-                        file.next().unwrap()
-                    );
+                    let Some(chr) = file.next() else {
+                        error(&mut out, diagnostics, AscError::UnexpectedEof { context: "byte declaration" }, file.span_from(start));
+                        continue 'tokens
+                    };
+                    val.push(chr);
                 }
 
-                byte += 2;
+                let span = file.span_from(start);
+
+                let Ok(val) = u8::from_str_radix(&val, 16) else {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "byte", text: format!("+{val}") }, span);
+                    continue
+                };
 
-                out.push(Token::Byte(
-                    u8::from_str_radix(&val, 16).expect(&format!("Expected hexadecimal value in byte declaration at character {byte}"))
-                ))
+                out.push(Token::new(TokenKind::Byte(val), span))
             }
             '0'..='9' | '-' => {
                 let mut num = String::from(chr);
 
-                while let Some('0'..='9') = file.peek() {
+                while let Some(chr) = file.peek() && continues_number(&num, chr) {
                     num.push(file.next().unwrap());
-                    byte += 1;
                 }
 
-                out.push(Token::Num(num.parse().unwrap()))
-            }
-            '"' => {
-                let mut string = ['\u{0}'; 8];
+                let span = file.span_from(start);
 
-                let mut count: usize = 0;
+                let Some(kind) = number(&num) else {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "number", text: num }, span);
+                    continue
+                };
 
-                while let Some(chr) = file.next() && chr != '"' {
-                    string[count] = chr;
-                    count += 1;
-                    if count == 8 {
-                        panic!("String overflow (You inputted a string that was over eight characters in length) at character {byte}")
-                    }
-                    byte += 1;
-                }
-
-                out.push(Token::Str(string))
+                out.push(Token::new(kind, span))
             }
-            '{' => {
-                let mut open_brackets = 1;
+            '\'' => {
+                let Some(bytes) = quoted(&mut file, start, '\'', "character literal", diagnostics) else {
+                    out.push(Token::new(TokenKind::Error, file.span_from(start)));
+                    continue
+                };
 
-                let mut to_eval = String::new();
+                let span = file.span_from(start);
 
-                while open_brackets != 0 {
-                    let Some(ch) = file.next() else {
-                        panic!("Unexpected EOF at character {byte}")
-                    };
+                let [byte] = bytes[..] else {
+                    let text = format!("'{}'", charset::escape(&bytes));
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "character", text }, span);
+                    continue
+                };
 
-                    byte += 1;
-
-                    match ch {
-                        '}' => {
-                            open_brackets -= 1;
-                            if open_brackets == 0 {
-                                continue
-                            }
-                            to_eval.push(chr)
-                        },
-                        any => to_eval.push(any)
-                    }
-                }
-
-                out.push(Token::Block(tokenize(to_eval)))
+                out.push(Token::new(TokenKind::Num(byte as i64), span))
             }
-            '[' => {
-                let mut open_brackets = 1;
-
-                let mut to_eval = String::new();
-
-                while open_brackets != 0 {
-                    let Some(ch) = file.next() else {
-                        panic!("Unexpected EOF at character {byte}")
-                    };
+            '"' => {
+                let Some(bytes) = quoted(&mut file, start, '"', "string", diagnostics) else {
+                    out.push(Token::new(TokenKind::Error, file.span_from(start)));
+                    continue
+                };
 
-                    byte += 1;
+                out.push(Token::new(TokenKind::Str(bytes), file.span_from(start)))
+            }
+            '{' => out.push(Token::new(TokenKind::OpenBrace, file.span_from(start))),
+            '}' => out.push(Token::new(TokenKind::CloseBrace, file.span_from(start))),
+            '[' => out.push(Token::new(TokenKind::OpenBracket, file.span_from(start))),
+            ']' => out.push(Token::new(TokenKind::CloseBracket, file.span_from(start))),
+            '!' => {
+                let mut label = String::new();
 
-                    match ch {
-                        ']' => {
-                            open_brackets -= 1;
-                            if open_brackets == 0 {
-                                continue
-                            }
-                            to_eval.push(chr)
-                        },
-                        any => to_eval.push(any)
+                if let Some(chr) = file.peek() && is_ident_start(chr) {
+                    while let Some(chr) = file.peek() && is_ident_continue(chr) {
+                        label.push(file.next().unwrap());
                     }
                 }
 
-                let arr = tokenize(to_eval);
-
-                if arr.len() != 8 {
-                    panic!("Array length can only be 8! Error at character {byte}")
+                if label.is_empty() {
+                    error(&mut out, diagnostics, AscError::InvalidLiteral { kind: "label", text: "!".to_string() }, file.span_from(start));
+                    continue
                 }
 
-                let mut arr_out = [0u8; 8];
-
-                arr.iter().enumerate().inspect(|(i, v)| {
-                    let (Token::Byte(v) | Token::Var(v)) = v else {
-                        panic!("You can only have bytes or variable pointers in arrays! Error at character {byte}")
-                    };
-
-                    arr_out[*i] = *v;
-                }).for_each(drop);
+                out.push(Token::new(TokenKind::Label(label), file.span_from(start)))
+            }
+            '=' => out.push(Token::new(TokenKind::Assign, file.span_from(start))),
+            ';' => out.push(Token::new(TokenKind::EndL, file.span_from(start))),
+            a if a.is_whitespace() => {  }
+            _   => error(&mut out, diagnostics, AscError::UnexpectedChar { chr }, file.span_from(start))
+        }
+    }
 
-                out.push(Token::Array(arr_out))
+    out
+}
+
+/// Reads the rest of a literal opened at `start` and closed by `quote`, decoding escapes and mapping each
+/// character to the console's character set. Every bad character is reported, and `None`
+/// returned if there were any.
+fn quoted(file: &mut Cursor, start: Pos, quote: char, context: &'static str, diagnostics: &mut Diagnostics) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut failed = false;
+
+    loop {
+        let at = file.pos;
+
+        let Some(chr) = file.next() else {
+            diagnostics.push(Diagnostic::new(AscError::UnexpectedEof { context }).at(file.span_from(start)));
+            return None
+        };
+
+        let byte = match chr {
+            chr if chr == quote => break,
+            '\\' => escape(file),
+            chr => charset::encode(chr).ok_or(AscError::UnmappableChar { chr }),
+        };
+
+        match byte {
+            Ok(byte) => bytes.push(byte),
+            Err(err) => {
+                diagnostics.push(Diagnostic::new(err).at(file.span_from(at)));
+                failed = true;
             }
-            '!' => {
-                let mut label = String::new();
+        }
+    }
 
-                while let Some('a'..='z') = file.peek() {
-                    label.push(file.next().unwrap());
-                    byte += 1;
+    (!failed).then_some(bytes)
+}
+
+/// Decodes the escape sequence after a `\`.
+fn escape(file: &mut Cursor) -> Result<u8, AscError> {
+    match file.next() {
+        Some('n') => Ok(b'\n'),
+        Some('t') => Ok(b'\t'),
+        Some('0') => Ok(0),
+        Some(chr @ ('\\' | '"' | '\'')) => Ok(chr as u8),
+        Some('x') => {
+            let mut hex = String::new();
+
+            for _ in 0..2 {
+                match file.peek() {
+                    Some(chr) if chr.is_ascii_hexdigit() => hex.push(file.next().unwrap()),
+                    _ => break,
                 }
-
-                out.push(Token::Label(label))
             }
-            ';' => out.push(Token::EndL),
-            a if a.is_whitespace() => {  }
-            _   => panic!("Unexpected char {chr} at character {byte}")
+
+            u8::from_str_radix(&hex, 16).map_err(|_| AscError::InvalidEscape { text: format!("\\x{hex}") })
         }
-        byte += 1;
+        Some(chr) => Err(AscError::InvalidEscape { text: format!("\\{chr}") }),
+        None => Err(AscError::InvalidEscape { text: "\\".to_string() }),
+    }
+}
+
+/// Whether `chr` carries on the number literal `text`: a digit, letter, `_` or `.`, or the
+/// sign of a decimal exponent.
+fn continues_number(text: &str, chr: char) -> bool {
+    match chr {
+        '+' | '-' => !is_prefixed(text) && text.ends_with(['e', 'E']),
+        chr => chr.is_ascii_alphanumeric() || chr == '_' || chr == '.',
+    }
+}
+
+fn is_prefixed(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+
+    ["0x", "0X", "0b", "0B", "0o", "0O"].iter().any(|prefix| digits.starts_with(prefix))
+}
+
+/// Parses a number literal: a decimal, `0x`/`0b`/`0o` integer, or a decimal with a
+/// fraction or exponent, which makes it a float. Any of them may be negative and use `_`
+/// to separate digits.
+fn number(text: &str) -> Option<TokenKind> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+
+    let radix = match digits.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0b" | "0B") => 2,
+        Some("0o" | "0O") => 8,
+        _ if digits.contains(['.', 'e', 'E']) => return float(text).map(TokenKind::Float),
+        _ => return integer(text, 10).map(TokenKind::Num),
+    };
+
+    integer(&format!("{sign}{}", &digits[2..]), radix).map(TokenKind::Num)
+}
+
+/// Parses an integer in `radix`, ignoring `_` separators between digits.
+fn integer(text: &str, radix: u32) -> Option<i64> {
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text),
+    };
+
+    if !separated_digits(digits, |chr| chr.is_digit(radix)) {
+        return None
     }
 
-    out
-}
\ No newline at end of file
+    i64::from_str_radix(&format!("{sign}{}", digits.replace('_', "")), radix).ok()
+}
+
+/// Parses a decimal float such as `1.5`, `-0.25` or `6.02e23`, ignoring `_` separators.
+/// A float too large for an `f64`, like `1e400`, is not valid.
+fn float(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+
+    // Keep Rust's own extras, like `inf` or a leading `+`, out of the language.
+    if !digits.starts_with(|chr: char| chr.is_ascii_digit())
+        || !separated_digits(digits, |chr| chr.is_ascii_digit() || matches!(chr, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return None
+    }
+
+    text.replace('_', "").parse().ok().filter(|float: &f64| float.is_finite())
+}
+
+/// Whether `digits` is made of characters accepted by `valid`, with `_` only between them.
+fn separated_digits(digits: &str, valid: impl Fn(char) -> bool) -> bool {
+    !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && digits.chars().all(|chr| chr == '_' || valid(chr))
+}
+
+/// The span of the `len` byte comment opener at `start`.
+fn opener(file: &Cursor, start: Pos, len: usize) -> Span {
+    Span { end: start.offset + len, ..file.span_from(start) }
+}
+
+/// Whether `chr` can start an identifier or label name.
+fn is_ident_start(chr: char) -> bool {
+    chr.is_ascii_alphabetic() || chr == '_'
+}
+
+/// Whether `chr` can continue an identifier or label name.
+fn is_ident_continue(chr: char) -> bool {
+    chr.is_ascii_alphanumeric() || chr == '_'
+}
+
+/// Reports `error` and leaves an error token in its place.
+fn error(out: &mut Vec<Token>, diagnostics: &mut Diagnostics, error: AscError, span: Span) {
+    diagnostics.push(Diagnostic::new(error).at(span));
+    out.push(Token::new(TokenKind::Error, span));
+}
@@ -1,30 +1,80 @@
+use std::fmt;
+
+use crate::charset;
+use crate::span::Span;
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
+pub enum TokenKind {
     Ident(String),
     Label(String),
-    Str([char; 8]),
+    /// A string already mapped to the console's character set.
+    Str(Vec<u8>),
     Var(u8),
     Num(i64),
     Byte(u8),
     Float(f64),
     Array([u8; 8]),
+    /// An array with variable names in it, each element a `Byte` or an `Ident`.
+    NamedArray(Vec<Token>),
     Block(Vec<Token>),
+    /// A use of the constant `name`, left by [`constants`](crate::constants) for the
+    /// parser to read as `value` where a value can go and as a plain name anywhere else.
+    Constant { name: String, value: Box<TokenKind> },
+    /// Delimiters, left by the tokenizer for the parser to pair up into blocks and arrays.
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    /// The `@` and `=` of `var name @ $addr = value`.
+    At,
+    Assign,
     EndL,
+    /// Stands in for text that failed to tokenize. The failure has already been
+    /// reported, so the compiler skips it without reporting it again.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn to_bytes(self) -> Vec<u8> {
+    pub fn new(kind: TokenKind, span: Span) -> Token {
+        Token { kind, span }
+    }
+}
+
+/// Writes the token back out the way it would be spelled in source.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Token::Ident(_) => panic!("Impossible to convert into bytes!"),
-            Token::Label(_) => panic!("Impossible to convert into bytes!"),
-            Token::Str(v) => Vec::from(v.map(|c| c as u8)),
-            Token::Byte(v) => vec![v],
-            Token::Var(v) => vec![v],
-            Token::Num(v) => Vec::from(v.to_le_bytes()),
-            Token::Float(v) => Vec::from(v.to_le_bytes()),
-            Token::Array(_) => todo!(),
-            Token::Block(_) => panic!("Impossible to convert into bytes!"),
-            Token::EndL => panic!("Impossible to convert into bytes!"),
+            TokenKind::Ident(ident) => write!(f, "{ident}"),
+            TokenKind::Label(label) => write!(f, "!{label}"),
+            TokenKind::Str(str) => write!(f, "\"{}\"", charset::escape(str)),
+            TokenKind::Var(addr) => write!(f, "${addr:02x}"),
+            TokenKind::Num(num) => write!(f, "{num}"),
+            TokenKind::Byte(byte) => write!(f, "+{byte:02x}"),
+            TokenKind::Float(float) => write!(f, "#{float}"),
+            TokenKind::Array(arr) => {
+                let bytes: Vec<String> = arr.iter().map(|byte| format!("+{byte:02x}")).collect();
+                write!(f, "[{}]", bytes.join(" "))
+            }
+            TokenKind::NamedArray(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.kind.to_string()).collect();
+                write!(f, "[{}]", elements.join(" "))
+            }
+            TokenKind::Block(_) => write!(f, "{{...}}"),
+            TokenKind::Constant { name, .. } => write!(f, "{name}"),
+            TokenKind::OpenBrace => write!(f, "{{"),
+            TokenKind::CloseBrace => write!(f, "}}"),
+            TokenKind::OpenBracket => write!(f, "["),
+            TokenKind::CloseBracket => write!(f, "]"),
+            TokenKind::At => write!(f, "@"),
+            TokenKind::Assign => write!(f, "="),
+            TokenKind::EndL => write!(f, ";"),
+            TokenKind::Error => write!(f, "<error>"),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,10 @@
+//! Helpers shared by the integration tests.
+
+use asc_script::{Diagnostics, SourceMap};
+
+/// Every diagnostic in `diagnostics` rendered without colour, for a failing test to print.
+pub fn report(diagnostics: &Diagnostics, sources: &SourceMap) -> String {
+    let rendered: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.render(sources, false)).collect();
+
+    rendered.join("\n")
+}
@@ -0,0 +1,65 @@
+//! The debug info `-g` writes next to an assembled program.
+
+use std::fs;
+use std::process::Command;
+
+use asc_script::Assembler;
+
+const GAME: &str = "\
+keeploop
+endhead
+var score = 0
+var title = \"hello world!\"
+routine clear { cls +00; flsh }
+!top
+rep 2 {
+    call clear
+}
+add score score title
+jmp top
+";
+
+/// Each unrolled copy of `clear` points at the routine's own lines, with the `call` and
+/// the time round the `rep` that put it there.
+const DBG: &str = "\
+0000  02  game.asc:2:1
+0002  0b  game.asc:3:1
+000d  16  game.asc:4:1
+0023  02  game.asc:5:17  <- call clear (game.asc:8:5)  <- rep 1 of 2 (game.asc:7:1)
+0025  01  game.asc:5:26  <- call clear (game.asc:8:5)  <- rep 1 of 2 (game.asc:7:1)
+0026  02  game.asc:5:17  <- call clear (game.asc:8:5)  <- rep 2 of 2 (game.asc:7:1)
+0028  01  game.asc:5:26  <- call clear (game.asc:8:5)  <- rep 2 of 2 (game.asc:7:1)
+0029  04  game.asc:10:1
+002d  09  game.asc:11:1
+";
+
+#[test]
+fn debug_info() {
+    let mut assembler = Assembler::new();
+    assembler.assemble_file("game.asc", GAME).unwrap();
+
+    assert_eq!(assembler.debug_info().to_string(), DBG);
+}
+
+#[test]
+fn written_with_dbg_added() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("debug");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("game.asc"), GAME).unwrap();
+
+    let assemble = |args: &[&str]| {
+        let status = Command::new(env!("CARGO_BIN_EXE_asc_script"))
+            .args(["game.asc", "-q"])
+            .args(args)
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    assemble(&["-o", "plain.atc"]);
+    assert!(!dir.join("plain.atc.dbg").exists());
+
+    assemble(&["-g", "-o", "out.atc"]);
+    assert_eq!(fs::read_to_string(dir.join("out.atc.dbg")).unwrap(), DBG);
+}
@@ -0,0 +1,318 @@
+//! The exact bytes every kind of instruction compiles to, and the errors `compile` reports.
+
+mod common;
+
+use asc_script::{Assembler, assemble};
+use common::report;
+
+/// Assembles `source` after an `endhead`, returning the code after the header.
+fn code(source: &str) -> Vec<u8> {
+    let bytes = program(&format!("endhead\n{source}"));
+
+    assert_eq!(bytes[0], 0x00, "the header should be just `endhead`");
+
+    bytes[1..].to_vec()
+}
+
+/// Assembles `source`, panicking with the diagnostics if it fails.
+fn program(source: &str) -> Vec<u8> {
+    let mut assembler = Assembler::new();
+
+    match assembler.assemble(source) {
+        Ok(bytes) => bytes,
+        Err(diagnostics) => panic!("`{source}` failed to assemble:\n{}", report(&diagnostics, assembler.sources())),
+    }
+}
+
+/// The codes of the errors `source` fails with.
+fn errors(source: &str) -> Vec<&'static str> {
+    match assemble(source) {
+        Ok(_) => panic!("`{source}` should not assemble"),
+        Err(diagnostics) => diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).map(|diagnostic| diagnostic.code()).collect(),
+    }
+}
+
+/// `tag` followed by `value` as 8 little-endian bytes.
+fn tagged(tag: u8, value: [u8; 8]) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    bytes.extend(value);
+    bytes
+}
+
+#[test]
+fn header() {
+    assert_eq!(program("endhead"), [0x00]);
+    assert_eq!(program("keeploop\nkeepopen\nendhead"), [0x02, 0x04, 0x00]);
+    assert_eq!(program("headerbytes +02 +00\ncls +01"), [0x02, 0x00, 0xfc, 0x01]);
+    assert_eq!(program("headerbytes +02 +07\nendhead"), [0x02, 0x07, 0x00]);
+
+    // Flags are written in canonical order, however they are set.
+    assert_eq!(program("keepopen\nkeeploop\nendhead"), [0x02, 0x04, 0x00]);
+    assert_eq!(program("headerbytes +07\nkeepopen\nendhead"), [0x04, 0x07, 0x00]);
+
+    // The bytes of the flags in `headerbytes` set the flags.
+    assert_eq!(program("headerbytes +07 +04 +02\nendhead"), [0x02, 0x04, 0x07, 0x00]);
+    assert_eq!(program("header { headerbytes +04; keeploop }"), [0x02, 0x04, 0x00]);
+
+    assert_eq!(program("header {}"), [0x00]);
+    assert_eq!(program("header { keepopen; keeploop }"), [0x02, 0x04, 0x00]);
+    assert_eq!(program("header { headerbytes +07 +08; keeploop }\ncls +01"), [0x02, 0x07, 0x08, 0x00, 0xfc, 0x01]);
+}
+
+#[test]
+fn header_errors() {
+    assert_eq!(errors("!top\nendhead"), ["E0108"]);
+    assert_eq!(errors("cls +01\nendhead"), ["E0110"]);
+    assert_eq!(errors("endhead\nkeeploop"), ["E0111"]);
+    assert_eq!(errors("headerbytes +00 +02"), ["E0111"]);
+    assert_eq!(errors("header { keeploop }\nendhead"), ["E0111"]);
+    assert_eq!(errors("cls +01\nheader {}"), ["E0110"]);
+
+    assert_eq!(errors("keeploop\nkeeploop\nendhead"), ["E0112"]);
+    assert_eq!(errors("header { keepopen; keepopen }"), ["E0112"]);
+    assert_eq!(errors("keeploop\nheaderbytes +02\nendhead"), ["E0112"]);
+    assert_eq!(errors("header { headerbytes +04 +04 }"), ["E0112"]);
+    assert_eq!(errors("header { cls +01; !top; endhead }"), ["E0113", "E0108", "E0113"]);
+    assert_eq!(errors("header { headerbytes +01 +00 }"), ["E0109"]);
+}
+
+#[test]
+fn plain_instructions() {
+    assert_eq!(code("noop"), [0x00]);
+    assert_eq!(code("cpix 1 2 +03"), [0x01, 0x01, 0x02, 0x03]);
+    assert_eq!(code("pix $00 $01 +04"), [0x02, 0x00, 0x01, 0x04]);
+    assert_eq!(
+        code("spr [$00 $01 $02 $03 $04 $05 $06 $07] $08 $09"),
+        [0x03, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09],
+    );
+    assert_eq!(code("gt $01 $02 $03"), [0xb1, 0x01, 0x02, 0x03]);
+    assert_eq!(code("lt $01 $02 $03"), [0xb2, 0x01, 0x02, 0x03]);
+    assert_eq!(code("key +05 $06"), [0xd0, 0x05, 0x06]);
+    assert_eq!(code("flsh"), [0xfb]);
+    assert_eq!(code("cls +0c"), [0xfc, 0x0c]);
+}
+
+#[test]
+fn arithmetic_opcodes() {
+    let ops = [
+        ("fdiv", 0xf0),
+        ("fsub", 0xf1),
+        ("fadd", 0xf2),
+        ("fmul", 0xf3),
+        ("div", 0xf4),
+        ("sub", 0xf5),
+        ("add", 0xf6),
+        ("mul", 0xf7),
+    ];
+
+    for (name, opcode) in ops {
+        assert_eq!(code(&format!("{name} $0a $0b $0c")), [opcode, 0x0a, 0x0b, 0x0c], "{name}");
+    }
+}
+
+#[test]
+fn constant_folding() {
+    let var = |tag: u8, value: [u8; 8], out: u8| {
+        let mut bytes = vec![0xa1];
+        bytes.extend(tagged(tag, value));
+        bytes.push(out);
+        bytes
+    };
+
+    assert_eq!(code("add 2 3 $04"), var(0xe0, 5i64.to_le_bytes(), 0x04));
+    assert_eq!(code("sub 2 3 $04"), var(0xe0, (-1i64).to_le_bytes(), 0x04));
+    assert_eq!(code("mul 6 7 $04"), var(0xe0, 42i64.to_le_bytes(), 0x04));
+    assert_eq!(code("div 7 2 $04"), var(0xe0, 3i64.to_le_bytes(), 0x04));
+    assert_eq!(code("fmul #1.5 #2 $04"), var(0xf0, 3.0f64.to_le_bytes(), 0x04));
+    assert_eq!(code("fdiv #1 #4 $04"), var(0xf0, 0.25f64.to_le_bytes(), 0x04));
+
+    assert_eq!(errors("endhead\ndiv 1 0 $00"), ["E0107"]);
+    assert_eq!(errors("endhead\nmul 9223372036854775807 2 $00"), ["E0107"]);
+    assert_eq!(errors("endhead\nfadd #1e308 #1e308 $00"), ["E0107"]);
+    assert_eq!(errors("endhead\nfdiv #0 #0 $00"), ["E0107"]);
+
+    // A float literal too large to fold with at all.
+    assert_eq!(errors("endhead\nfadd #1e400 #1 $00"), ["E0002"]);
+    assert_eq!(errors("endhead\nvar $00 1e400"), ["E0002"]);
+}
+
+#[test]
+fn variables() {
+    let init = |opcode: u8, value: Vec<u8>, addr: u8| {
+        let mut bytes = vec![opcode];
+        bytes.extend(value);
+        bytes.push(addr);
+        bytes
+    };
+
+    assert_eq!(code("var $05 42"), init(0xa1, tagged(0xe0, 42i64.to_le_bytes()), 0x05));
+    assert_eq!(code("let $05 -1"), init(0xa2, tagged(0xe0, [0xff; 8]), 0x05));
+    assert_eq!(code("var $05 #1.5"), init(0xa1, tagged(0xf0, 1.5f64.to_le_bytes()), 0x05));
+    assert_eq!(code("var $05 [+01 +02 +03 +04 +05 +06 +07 +08]"), init(0xa1, tagged(0x8a, [1, 2, 3, 4, 5, 6, 7, 8]), 0x05));
+    assert_eq!(code("var $05 \"\""), init(0xa1, tagged(0xab, [0; 8]), 0x05));
+
+    let mut long = init(0xa1, tagged(0xab, *b"HELLO WO"), 0x10);
+    long.extend(init(0xa1, tagged(0xab, *b"RLD\0\0\0\0\0"), 0x11));
+    assert_eq!(code("var $10 \"HELLO WORLD\""), long);
+
+    assert_eq!(errors("endhead\nvar $ff \"123456789\""), ["E0004"]);
+}
+
+#[test]
+fn variable_names() {
+    let set = |addr: u8, value: i64| {
+        let mut bytes = vec![0xa1];
+        bytes.extend(tagged(0xe0, value.to_le_bytes()));
+        bytes.push(addr);
+        bytes
+    };
+
+    // Names get the lowest addresses no `$var` uses, in order of declaration.
+    assert_eq!(code("var a = 1\nvar b = 2"), [set(0x00, 1), set(0x01, 2)].concat());
+    assert_eq!(code("var a = 1\nvar $00 2"), [set(0x01, 1), set(0x00, 2)].concat());
+    assert_eq!(code("var a @ $40 = 1\nvar a 2"), [set(0x40, 1), set(0x40, 2)].concat());
+    assert_eq!(code("var up @ $d1\nkey +01 up"), [0xd0, 0x01, 0xd1]);
+
+    // A long string takes a run of free variables.
+    let mut bytes = set(0x01, 0);
+    bytes.extend([0xa1, 0xab]);
+    bytes.extend(*b"HELLO WO");
+    bytes.extend([0x02, 0xa1, 0xab]);
+    bytes.extend(*b"RLD\0\0\0\0\0");
+    bytes.push(0x03);
+    bytes.extend(set(0x00, 0));
+    assert_eq!(code("var $01 0\nvar title = \"HELLO WORLD\"\nvar $00 0"), bytes);
+
+    // Names stand for their address in every `$var` position and in arrays, even before
+    // they are declared.
+    assert_eq!(
+        code("spr [a a a a b b b b] a b\nvar a @ $0a\nvar b @ $0b"),
+        [0x03, 0x0a, 0x0a, 0x0a, 0x0a, 0x0b, 0x0b, 0x0b, 0x0b, 0x0a, 0x0b],
+    );
+    assert_eq!(code("var a = 1\nadd a a a"), [set(0x00, 1), vec![0xf6, 0x00, 0x00, 0x00]].concat());
+
+    assert_eq!(errors("endhead\nadd a a a"), ["E0114", "E0114", "E0114"]);
+    assert_eq!(errors("endhead\nvar a = 1\nlet a = 2"), ["E0115"]);
+    assert_eq!(errors(&format!("endhead\nvar a = \"{}\"", "a".repeat(2049))), ["E0116"]);
+    assert_eq!(errors("endhead\nvar a @ b"), ["E0102"]);
+}
+
+#[test]
+fn constants() {
+    assert_eq!(code("const RED = +0c\ncls RED"), [0xfc, 0x0c]);
+    assert_eq!(code("RED equ 12\ncls RED"), [0xfc, 0x0c]);
+    assert_eq!(code("const X = 3\nconst Y = X\ncpix X Y +01"), [0x01, 0x03, 0x03, 0x01]);
+    assert_eq!(code("const N = 3\nrep N { flsh }"), [0xfb, 0xfb, 0xfb]);
+    assert_eq!(code("const A = +aa\nspr [A A A A A A A A] $00 $01"), [0x03, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0x00, 0x01]);
+    assert_eq!(code("const F = #1.5\nvar $05 F"), [&[0xa1][..], &tagged(0xf0, 1.5f64.to_le_bytes()), &[0x05]].concat());
+
+    // The value is checked where the constant is used.
+    assert_eq!(errors("endhead\nconst BIG = 300\ncls BIG"), ["E0109"]);
+
+    assert_eq!(errors("endhead\nconst X = 1\nconst X = 2"), ["E0117"]);
+    assert_eq!(errors("endhead\nconst cls = 1"), ["E0118"]);
+    assert_eq!(errors("endhead\nconst X = $01"), ["E0102"]);
+    assert_eq!(errors("endhead\nconst X =\nflsh"), ["E0103"]);
+
+    // Only values are replaced, so labels and routines may share a constant's name.
+    let mut bytes = vec![0xe3];
+    bytes.extend(0u64.to_le_bytes());
+    assert_eq!(code("const loop = 5\n!loop\njmp loop"), bytes);
+    assert_eq!(code("const r = 5\nroutine r { cls r }\ncall r"), [0xfc, 0x05]);
+
+    // Variable names may not, as they can go where values can.
+    assert_eq!(errors("endhead\nconst x = 5\nvar x = 1"), ["E0120"]);
+    assert_eq!(errors("endhead\nvar x = 1\nconst x = 5"), ["E0120"]);
+}
+
+#[test]
+fn jumps() {
+    let addr = |addr: u64| addr.to_le_bytes();
+
+    let mut bytes = vec![0xe1, 0x03];
+    bytes.extend(addr(0));
+    assert_eq!(code("!top\ntjmp $03 top"), bytes);
+
+    // `jmp` is 9 bytes and `flsh` 1, so `end` is at 10.
+    let mut bytes = vec![0xe3];
+    bytes.extend(addr(10));
+    bytes.push(0xfb);
+    assert_eq!(code("jmp end\nflsh\n!end"), bytes);
+
+    let mut bytes = vec![0xe2, 0x01];
+    bytes.extend(addr(2));
+    assert_eq!(code("cls +01\n!here\nfjmp $01 here"), [&[0xfc, 0x01][..], &bytes].concat());
+
+    let mut bytes = vec![0xe3];
+    bytes.extend(addr(1234));
+    assert_eq!(code("bjmp 1234"), bytes);
+
+    // Code addresses start after the header.
+    let mut bytes = vec![0x02, 0x04, 0x00, 0xfc, 0x01, 0xe3];
+    bytes.extend(addr(0));
+    assert_eq!(program("keeploop\nkeepopen\nendhead\n!a\ncls +01\njmp a"), bytes);
+
+    assert_eq!(errors("endhead\njmp nowhere"), ["E0104"]);
+    assert_eq!(errors("endhead\n!a\njmp a\nflsh\n!a\njmp a"), ["E0119"]);
+    assert_eq!(errors("endhead\nrep 2 { !a; flsh }"), ["E0119"]);
+    assert_eq!(errors("endhead\nroutine r { !a; flsh }\ncall r\ncall r"), ["E0119"]);
+}
+
+#[test]
+fn blocks() {
+    // The `fjmp` of an `if` goes just past its block.
+    let mut bytes = vec![0xe2, 0x07];
+    bytes.extend(11u64.to_le_bytes());
+    bytes.push(0xfb);
+    assert_eq!(code("if $07 { flsh }"), bytes);
+
+    assert_eq!(code("rep 3 { flsh }"), [0xfb, 0xfb, 0xfb]);
+    assert_eq!(code("rep 0 { flsh }"), []);
+
+    assert_eq!(code("routine r { cls +01 }\ncall r\ncall r"), [0xfc, 0x01, 0xfc, 0x01]);
+    assert_eq!(code("routine r { cls +01 }"), []);
+
+    assert_eq!(errors("endhead\ncall r"), ["E0105"]);
+    assert_eq!(errors("endhead\nroutine r { call r }\ncall r"), ["E0106"]);
+    assert_eq!(errors("endhead\nrep 99999999999 { flsh }"), ["E0109"]);
+    assert_eq!(errors("endhead\nrep -1 { flsh }"), ["E0109"]);
+
+    // Nested `rep`s are limited by how many times they repeat their code in all.
+    assert_eq!(code("rep 2 { rep 3 { flsh } }"), [0xfb; 6]);
+    assert_eq!(code("rep 0 { rep 65535 { rep 2 { flsh } } }"), []);
+    assert_eq!(errors("endhead\nrep 256 { rep 256 { flsh } }"), ["E0121"]);
+    assert_eq!(errors("endhead\nroutine r { rep 300 { flsh } }\nrep 300 { call r }"), ["E0121"]);
+}
+
+#[test]
+fn recovery() {
+    // A broken statement is skipped up to the `;` or `}` ending it, so each error is
+    // reported once and what follows it still compiles.
+    let source = "endhead\nif $01 { pxi $01 +01 +02 } flsh\ncls +01 $02 { noop }; var = 3 @\nflsh";
+
+    let diagnostics = assemble(source).unwrap_err();
+    let found: Vec<(&str, usize, usize)> = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.code(), diagnostic.span.unwrap().line, diagnostic.span.unwrap().col))
+        .collect();
+
+    assert_eq!(found, [("E0100", 2, 10), ("E0101", 3, 9), ("E0102", 3, 27)]);
+}
+
+#[test]
+fn readme_example() {
+    let readme = include_str!("../README.md");
+    let (_, example) = readme.split_once("Syntax:\n```\n").unwrap();
+    let (example, _) = example.split_once("```").unwrap();
+
+    let mut bytes = vec![0x02, 0x00];
+    bytes.push(0xa2);
+    bytes.extend(tagged(0xe0, 0i64.to_le_bytes()));
+    bytes.push(0x00);
+    bytes.push(0xa1);
+    bytes.extend(tagged(0xe0, 1i64.to_le_bytes()));
+    bytes.push(0x01);
+    bytes.extend([0xf6, 0x00, 0x01, 0x00]);
+    bytes.extend([0x02, 0x00, 0x00, 0xab]);
+
+    assert_eq!(program(example), bytes);
+}
@@ -0,0 +1,173 @@
+//! `--message-format json`, read back with a small JSON parser to check it is valid JSON
+//! of the documented shape.
+
+use std::iter::Peekable;
+use std::process::Command;
+use std::str::Chars;
+
+use asc_script::diagnostics::Suggestion;
+use asc_script::{AscError, Diagnostic, SourceMap, Span};
+
+/// The subset of JSON diagnostics are written in.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Num(i64),
+    Str(String),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Json {
+        let mut chars = text.chars().peekable();
+        let value = value(&mut chars);
+        assert_eq!(chars.next(), None, "trailing text after the value in `{text}`");
+        value
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Json::Obj(fields) => fields.iter().map(|(key, _)| key.as_str()).collect(),
+            _ => panic!("{self:?} is not an object"),
+        }
+    }
+
+    fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Obj(fields) => &fields.iter().find(|(k, _)| k == key).unwrap().1,
+            _ => panic!("{self:?} is not an object"),
+        }
+    }
+
+    fn str(&self) -> &str {
+        match self {
+            Json::Str(text) => text,
+            _ => panic!("{self:?} is not a string"),
+        }
+    }
+}
+
+fn value(chars: &mut Peekable<Chars>) -> Json {
+    match chars.peek() {
+        Some('n') => {
+            assert_eq!(chars.by_ref().take(4).collect::<String>(), "null");
+            Json::Null
+        }
+        Some('"') => Json::Str(string(chars)),
+        Some('{') => {
+            chars.next();
+            let mut fields = vec![];
+
+            while chars.peek() != Some(&'}') {
+                if !fields.is_empty() {
+                    assert_eq!(chars.next(), Some(','));
+                }
+                let key = string(chars);
+                assert_eq!(chars.next(), Some(':'));
+                fields.push((key, value(chars)));
+            }
+
+            chars.next();
+            Json::Obj(fields)
+        }
+        _ => {
+            let mut digits = String::new();
+            while let Some(&c) = chars.peek() && (c.is_ascii_digit() || c == '-') {
+                digits.push(c);
+                chars.next();
+            }
+            Json::Num(digits.parse().unwrap_or_else(|_| panic!("expected a value, found `{digits}`")))
+        }
+    }
+}
+
+fn string(chars: &mut Peekable<Chars>) -> String {
+    assert_eq!(chars.next(), Some('"'));
+    let mut text = String::new();
+
+    loop {
+        match chars.next().expect("unterminated string") {
+            '"' => return text,
+            '\\' => match chars.next().unwrap() {
+                '"' => text.push('"'),
+                '\\' => text.push('\\'),
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    text.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                }
+                c => panic!("invalid escape `\\{c}`"),
+            },
+            c if u32::from(c) < 0x20 => panic!("unescaped control character {c:?}"),
+            c => text.push(c),
+        }
+    }
+}
+
+#[test]
+fn message_format() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("message_format.asc");
+    std::fs::write(&path, "ld $01\npix \"a\\nb\" $01 +01\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asc_script"))
+        .args(["check", "--message-format", "json"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let diagnostics: Vec<Json> = stdout.lines().map(Json::parse).collect();
+    assert_eq!(diagnostics.len(), 2);
+
+    for diagnostic in &diagnostics {
+        assert_eq!(diagnostic.keys(), ["code", "severity", "lint", "message", "help", "span", "suggestion"]);
+        assert_eq!(diagnostic.get("span").keys(), ["file", "line", "column", "start", "end"]);
+        assert_eq!(diagnostic.get("span").get("file").str(), path.to_str().unwrap());
+        assert_eq!(diagnostic.get("severity").str(), "error");
+        assert_eq!(diagnostic.get("lint"), &Json::Null);
+    }
+
+    let unknown = &diagnostics[0];
+    assert_eq!(unknown.get("code").str(), "E0100");
+    assert_eq!(unknown.get("help"), &Json::Null);
+    assert_eq!(unknown.get("suggestion").keys(), ["message", "replacement", "span"]);
+    assert_eq!(unknown.get("suggestion").get("replacement").str(), "lt");
+    assert_eq!(unknown.get("suggestion").get("span"), unknown.get("span"));
+
+    let operand = &diagnostics[1];
+    assert_eq!(operand.get("code").str(), "E0102");
+    assert_eq!(operand.get("message").str(), "Unexpected token `\"a\\nb\"` in pix");
+    assert_eq!(operand.get("help").str(), "pix expects `$var $var +byte`");
+    assert_eq!(operand.get("suggestion"), &Json::Null);
+
+    let span = operand.get("span");
+    let position: Vec<&Json> = ["line", "column", "start", "end"].map(|key| span.get(key)).to_vec();
+    assert_eq!(position, [&Json::Num(2), &Json::Num(5), &Json::Num(11), &Json::Num(17)]);
+}
+
+#[test]
+fn escapes() {
+    let mut sources = SourceMap::new();
+    let file = sources.add("dir\\\"quoted\".asc", "");
+    let span = Span { file, start: 0, end: 0, line: 1, col: 1 };
+
+    let text = "\"\\\n\r\t\u{1}\u{1f}é";
+    let diagnostic = Diagnostic::new(AscError::InvalidEscape { text: text.to_string() })
+        .at(span)
+        .suggest(Some(Suggestion { message: format!("not {text}"), span, replacement: text.to_string() }));
+
+    let json = diagnostic.to_json(&sources);
+    assert!(json.contains(r#"\"\\\n\r\t\u0001\u001fé"#), "{json}");
+
+    let json = Json::parse(&json);
+    assert_eq!(json.get("message").str(), format!("Unknown escape sequence `{text}`"));
+    assert_eq!(json.get("span").get("file").str(), "dir\\\"quoted\".asc");
+
+    let suggestion = json.get("suggestion");
+    assert_eq!(suggestion.get("message").str(), format!("not {text}"));
+    assert_eq!(suggestion.get("replacement").str(), text);
+    assert_eq!(suggestion.get("span"), json.get("span"));
+}
@@ -0,0 +1,134 @@
+//! Assembling files into objects, and linking objects into one program.
+
+mod common;
+
+use asc_script::{Assembler, Object, assemble, link};
+use common::report;
+
+/// Assembles `source` as the object `name`, panicking with the diagnostics if it fails.
+fn object(name: &str, source: &str) -> Object {
+    let mut assembler = Assembler::new();
+
+    match assembler.assemble_object(name, source) {
+        Ok(object) => object,
+        Err(diagnostics) => panic!("`{name}` failed to assemble:\n{}", report(&diagnostics, assembler.sources())),
+    }
+}
+
+#[test]
+fn call_in_header() {
+    let object = object("p.asc", "routine h { keeploop }\ncall h\nendhead\nflsh");
+
+    assert_eq!(object.header, [0x02]);
+    assert_eq!(object.code, [0xfb]);
+    assert!(object.expansions.is_empty(), "the expansion of `h` is in the header, not the code");
+}
+
+#[test]
+fn imported_routines_use_their_own_names() {
+    let library = object("lib.asc", "endhead\nvar lives = 3\nroutine hurt { sub lives lives lives }");
+
+    let mut assembler = Assembler::new();
+    assembler.import(&library).unwrap();
+
+    // Names the library declares mean nothing here, so a file may declare its own.
+    assert_eq!(assembler.assemble("endhead\nflsh").unwrap(), [0x00, 0xfb]);
+    assert!(assembler.assemble("endhead\nsub lives lives lives").is_err());
+
+    // `lives` gets the lowest address the library's `lives` does not use.
+    assert_eq!(
+        assembler.assemble("endhead\nvar lives = 9\ncall hurt").unwrap(),
+        assemble("endhead\nvar $01 9\nsub $00 $00 $00").unwrap(),
+    );
+}
+
+#[test]
+fn overlapping_variables() {
+    let game = object("game.asc", "endhead\nvar score = 0\nflsh");
+    let library = object("lib.asc", "endhead\nvar lives = 3");
+
+    let diagnostics = link(&[game, library.clone()]).unwrap_err();
+    let codes: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.code()).collect();
+    assert_eq!(codes, ["E0203"]);
+
+    // The same name pinned to the same variable is shared.
+    let first = object("a.asc", "endhead\nvar lives @ $00");
+    assert!(link(&[first, library.clone()]).is_ok());
+
+    // Names are given addresses the names of imported objects do not use.
+    let mut assembler = Assembler::new();
+    assembler.import(&library).unwrap();
+    let game = assembler.assemble_object("game.asc", "endhead\nvar score = 0\nflsh").unwrap();
+    assert_eq!(game.slots[0].addr, 0x01);
+    assert!(link(&[game, library]).is_ok());
+}
+
+#[test]
+fn relocation() {
+    let game = object("game.asc", "keeploop\nendhead\n!top\nflsh\nif $01 { jmp top }\njmp end");
+    let library = object("lib.asc", "keepopen\nendhead\nnoop\n!end\nif $02 { noop }\njmp end");
+
+    assert_eq!(game.imports(), ["end"]);
+    assert!(library.imports().is_empty());
+
+    // The library's labels and the end of its `if` move by the size of the game's code,
+    // just as if both files were one.
+    let linked = link(&[game, library]).unwrap();
+    let whole = assemble(
+        "keeploop\nkeepopen\nendhead\n!top\nflsh\nif $01 { jmp top }\njmp end\nnoop\n!end\nif $02 { noop }\njmp end",
+    );
+    assert_eq!(linked, whole.unwrap());
+}
+
+#[test]
+fn link_errors() {
+    let codes = |objects: &[Object]| -> Vec<&str> {
+        link(objects).unwrap_err().iter().map(|diagnostic| diagnostic.code()).collect()
+    };
+
+    let game = object("game.asc", "endhead\njmp end");
+
+    assert_eq!(codes(std::slice::from_ref(&game)), ["E0201"]);
+
+    let first = object("a.asc", "endhead\n!end\nflsh");
+    let second = object("b.asc", "endhead\n!end\nnoop");
+    assert_eq!(codes(&[game, first.clone(), second.clone()]), ["E0202"]);
+
+    // Labels only one object jumps to are its own, even if another defines them too.
+    assert!(link(&[first, second]).is_ok());
+}
+
+#[test]
+fn object_round_trip() {
+    let source = "keeploop\nendhead\nvar lives = 3\nroutine hurt { sub lives lives lives }\n!top\ncall hurt\nif $01 { jmp top }\njmp end";
+    let object = object("game.asc", source);
+
+    assert!(!object.labels.is_empty());
+    assert!(!object.relocations.is_empty());
+    assert!(!object.expansions.is_empty());
+    assert!(!object.slots.is_empty());
+    assert!(!object.debug.is_empty());
+
+    let bytes = object.to_bytes();
+    assert_eq!(Object::from_bytes(&bytes), Ok(object));
+
+    assert!(Object::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Object::from_bytes(b"ASCX").is_err());
+}
+
+#[test]
+fn linked_header() {
+    let game = object("game.asc", "keeploop\nheaderbytes +07 +07\nendhead\nflsh");
+    let library = object("lib.asc", "keepopen\nendhead\nnoop");
+
+    // Flags are merged, raw bytes kept as written.
+    assert_eq!(link(&[game.clone(), library]).unwrap()[..5], [0x02, 0x04, 0x07, 0x07, 0x00]);
+
+    let same = object("same.asc", "headerbytes +07 +07\nendhead");
+    assert_eq!(link(&[game.clone(), same]).unwrap()[..4], [0x02, 0x07, 0x07, 0x00]);
+
+    let other = object("other.asc", "headerbytes +08\nendhead");
+    let diagnostics = link(&[game, other]).unwrap_err();
+    let codes: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.code()).collect();
+    assert_eq!(codes, ["E0204"]);
+}
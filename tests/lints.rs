@@ -0,0 +1,107 @@
+//! The warnings each lint gives, and how `-W`, `-A` and `-D` change them.
+
+use asc_script::{Assembler, Diagnostics, Level, Lint, LintLevels};
+
+/// Assembles `source` with the lint levels `levels` sets, returning the diagnostics
+/// whether or not it assembled.
+fn assemble(source: &str, levels: impl FnOnce(&mut LintLevels)) -> (bool, Diagnostics, Assembler) {
+    let mut assembler = Assembler::new();
+    levels(assembler.lints_mut());
+
+    match assembler.assemble(source) {
+        Ok(_) => {
+            let warnings = assembler.warnings().clone();
+            (true, warnings, assembler)
+        }
+        Err(diagnostics) => (false, diagnostics, assembler),
+    }
+}
+
+/// The codes of the warnings `source` gives at the default levels.
+fn warnings(source: &str) -> Vec<&'static str> {
+    let (assembled, diagnostics, _) = assemble(source, |_| {});
+    assert!(assembled, "`{source}` should assemble");
+
+    diagnostics.iter().map(|diagnostic| diagnostic.code()).collect()
+}
+
+#[test]
+fn each_lint() {
+    assert_eq!(warnings("endhead\nvar $01 3"), ["W0001"]);
+    assert!(warnings("endhead\nvar $01 3\nsub $01 $01 $01").is_empty());
+
+    assert_eq!(warnings("endhead\n!top\njmp top\nflsh"), ["W0002"]);
+    assert!(warnings("endhead\n!top\nflsh\njmp back\n!back\njmp top").is_empty());
+
+    assert_eq!(warnings("endhead\n!top\nflsh"), ["W0003"]);
+    assert!(warnings("endhead\n!top\nflsh\njmp top").is_empty());
+
+    assert_eq!(warnings("endhead\nvar $01 3\nlet $01 4\nsub $01 $01 $01"), ["W0004"]);
+    assert!(warnings("endhead\nlet $01 4\nsub $01 $01 $01").is_empty());
+
+    assert_eq!(warnings("endhead\nroutine hurt { flsh }"), ["W0005"]);
+    assert!(warnings("endhead\nroutine hurt { flsh }\ncall hurt").is_empty());
+}
+
+#[test]
+fn levels() {
+    let source = "endhead\n!top\nroutine hurt { flsh }";
+
+    let (assembled, diagnostics, _) = assemble(source, |lints| lints.set(Lint::UnusedLabel, Level::Allow));
+    assert!(assembled);
+    assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.code()).collect::<Vec<_>>(), ["W0005"]);
+
+    let (assembled, diagnostics, _) = assemble(source, |lints| lints.set_all(Level::Allow));
+    assert!(assembled);
+    assert!(diagnostics.is_empty());
+
+    let (assembled, diagnostics, _) = assemble(source, |lints| lints.set(Lint::UnusedLabel, Level::Deny));
+    assert!(!assembled);
+    let severities: Vec<(&str, bool)> = diagnostics.iter().map(|diagnostic| (diagnostic.code(), diagnostic.is_error())).collect();
+    assert_eq!(severities, [("W0003", true), ("W0005", false)]);
+
+    // The last flag wins, so `-D warnings -A unused-label` denies every lint but one.
+    let (assembled, diagnostics, _) = assemble(source, |lints| {
+        lints.set_all(Level::Deny);
+        lints.set(Lint::UnusedLabel, Level::Allow);
+    });
+    assert!(!assembled);
+    let severities: Vec<(&str, bool)> = diagnostics.iter().map(|diagnostic| (diagnostic.code(), diagnostic.is_error())).collect();
+    assert_eq!(severities, [("W0005", true)]);
+
+    // `-A unused-label -W warnings` brings it back.
+    let (assembled, diagnostics, _) = assemble(source, |lints| {
+        lints.set(Lint::UnusedLabel, Level::Allow);
+        lints.set_all(Level::Warn);
+    });
+    assert!(assembled);
+    assert_eq!(diagnostics.len(), 2);
+}
+
+#[test]
+fn notes() {
+    let notes = |levels: fn(&mut LintLevels)| -> Vec<String> {
+        let (_, diagnostics, assembler) = assemble("endhead\n!top", levels);
+
+        diagnostics.iter()
+            .flat_map(|diagnostic| diagnostic.render(assembler.sources(), false).lines().map(str::to_string).collect::<Vec<_>>())
+            .filter(|line| line.contains("note:"))
+            .collect()
+    };
+
+    assert_eq!(notes(|_| {}), ["  = note: silence this warning with `-A unused-label`"]);
+    assert_eq!(
+        notes(|lints| lints.set(Lint::UnusedLabel, Level::Deny)),
+        ["  = note: this lint was denied by `-D unused-label`"],
+    );
+    assert_eq!(notes(|lints| lints.set_all(Level::Deny)), ["  = note: this lint was denied by `-D warnings`"]);
+
+    // Naming the lint after `warnings` takes over.
+    assert_eq!(
+        notes(|lints| {
+            lints.set_all(Level::Deny);
+            lints.set(Lint::UnusedLabel, Level::Deny);
+        }),
+        ["  = note: this lint was denied by `-D unused-label`"],
+    );
+}
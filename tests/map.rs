@@ -0,0 +1,68 @@
+//! The symbol map written next to an assembled program.
+
+use std::fs;
+use std::process::Command;
+
+use asc_script::Assembler;
+
+const GAME: &str = "\
+keeploop
+endhead
+var score = 0
+var title = \"hello world!\"
+var lives @ $40
+routine clear { cls +00; flsh }
+!top
+rep 2 {
+    call clear
+}
+add score score title
+sub lives lives lives
+jmp top
+";
+
+/// Every expansion of `clear` is listed at the `call` in the `rep`, once per time round it.
+const MAP: &str = "\
+header: 2 bytes, code address 0 is file offset 0x0002
+
+labels:
+    0x0021  top
+
+routines:
+    0x0021..0x0024  clear  called at game.asc:9
+    0x0024..0x0027  clear  called at game.asc:9
+
+variables:
+    $00  num    0  set at game.asc:3
+    $01  str    \"hello wo\"  set at game.asc:4
+    $02  str    \"rld!\"  set at game.asc:4
+
+names:
+    $00      score  allocated at game.asc:3
+    $01-$02  title  allocated at game.asc:4
+    $40      lives  pinned at game.asc:5
+";
+
+#[test]
+fn symbol_map() {
+    let mut assembler = Assembler::new();
+    assembler.assemble_file("game.asc", GAME).unwrap();
+
+    assert_eq!(assembler.symbol_map().to_string(), MAP);
+}
+
+#[test]
+fn written_next_to_output() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("map");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("game.asc"), GAME).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_asc_script"))
+        .args(["game.asc", "-o", "out.atc", "-q"])
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    assert_eq!(fs::read_to_string(dir.join("out.map")).unwrap(), MAP);
+}
@@ -0,0 +1,311 @@
+//! Random programs must assemble, disassemble and reassemble to the same bytes.
+//!
+//! Programs are generated from the instruction table, so every instruction and form is
+//! covered as soon as it is added. A failure prints the seed and the program.
+
+mod common;
+
+use asc_script::disasm::disassemble;
+use asc_script::isa::{self, Instruction, OperandType};
+use asc_script::{Assembler, assemble};
+use common::report;
+
+const CASES: u64 = 500;
+
+/// xorshift64*, so runs are the same everywhere without any dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        let span = max as i128 - min as i128 + 1;
+
+        (min as i128 + self.next() as i128 % span) as i64
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const LABELS: [&str; 3] = ["top", "middle", "end"];
+
+struct Generator {
+    rng: Rng,
+    routines: Vec<String>,
+    /// Variable names, which can be used anywhere in the file.
+    names: Vec<String>,
+    /// Constants defined at the top of the file, with their values.
+    constants: Vec<(String, String)>,
+}
+
+impl Generator {
+    fn program(&mut self) -> String {
+        let mut lines = vec![];
+
+        for i in 0..self.rng.below(4) {
+            let name = format!("CONST_{i}");
+            let value = match self.rng.below(3) {
+                0 => self.rng.range(0, 3).to_string(),
+                1 => format!("+{:02x}", self.rng.below(256)),
+                _ => self.fold_float(),
+            };
+
+            lines.push(if self.rng.chance(2) { format!("const {name} = {value}") } else { format!("{name} equ {value}") });
+            self.constants.push((name, value));
+        }
+
+        // Each flag at most once, in any order, then some raw bytes.
+        let mut header = vec![];
+        for flag in ["keeploop", "keepopen"] {
+            if self.rng.chance(2) {
+                header.insert(self.rng.below(header.len() + 1), flag.to_string());
+            }
+        }
+        if self.rng.chance(3) {
+            // Not the bytes of the flags, which may already be set.
+            let byte = loop {
+                let byte = self.rng.range(1, 255);
+
+                if byte != 0x02 && byte != 0x04 {
+                    break byte
+                }
+            };
+            header.push(format!("headerbytes +{byte:02x}"));
+        }
+
+        match self.rng.below(3) {
+            0 => lines.push(format!("header {{ {} }}", header.join("; "))),
+            1 => {
+                lines.extend(header);
+                lines.push("headerbytes +00".to_string());
+            }
+            _ => {
+                lines.extend(header);
+                lines.push("endhead".to_string());
+            }
+        }
+
+        self.names = (0..self.rng.below(4)).map(|i| format!("name_{i}")).collect();
+
+        for i in 0..self.rng.below(3) {
+            let body = self.block(1);
+            let name = format!("routine_{i}");
+
+            lines.push(format!("routine {name} {body}"));
+            self.routines.push(name);
+        }
+
+        let mut stmts: Vec<String> = (0..self.rng.range(1, 30)).map(|_| self.instr(0)).collect();
+
+        for name in self.names.clone() {
+            let decl = self.decl(&name);
+            stmts.insert(self.rng.below(stmts.len() + 1), decl);
+        }
+
+        // Every label is defined exactly once, anywhere in the code.
+        for label in LABELS {
+            let at = self.rng.below(stmts.len() + 1);
+            stmts.insert(at, format!("!{label}"));
+        }
+
+        lines.extend(stmts);
+
+        lines.join(if self.rng.chance(2) { "\n" } else { "; " })
+    }
+
+    /// Declares `name`, pinned to an address, given a value or both.
+    fn decl(&mut self, name: &str) -> String {
+        let keyword = self.rng.pick(&["var", "let"]);
+        // Leave room for a string of up to 3 variables.
+        let pin = format!("@ ${:02x}", self.rng.range(0, 0xfd));
+
+        match self.rng.below(3) {
+            0 => format!("{keyword} {name} {pin}"),
+            1 => format!("{keyword} {name} = {}", self.value()),
+            _ => format!("{keyword} {name} {pin} = {}", self.value()),
+        }
+    }
+
+    /// A constant whose value is accepted by `accepts`, if there is one.
+    fn constant(&mut self, accepts: impl Fn(&str) -> bool) -> Option<String> {
+        let constants: Vec<String> = self.constants.iter()
+            .filter(|(_, value)| accepts(value))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        (!constants.is_empty()).then(|| self.rng.pick(&constants).clone())
+    }
+
+    /// A `$var`, or sometimes a variable name.
+    fn var(&mut self, max: i64) -> String {
+        if !self.names.is_empty() && self.rng.chance(4) {
+            return self.rng.pick(&self.names).clone()
+        }
+
+        format!("${:02x}", self.rng.range(0, max))
+    }
+
+    fn block(&mut self, depth: usize) -> String {
+        let stmts: Vec<String> = (0..self.rng.below(4)).map(|_| self.instr(depth)).collect();
+
+        format!("{{ {} }}", stmts.join("; "))
+    }
+
+    fn instr(&mut self, depth: usize) -> String {
+        let instructions: Vec<&Instruction> = isa::INSTRUCTIONS.iter()
+            .filter(|instruction| !instruction.header && instruction.mnemonic != "routine")
+            .filter(|instruction| instruction.mnemonic != "call" || !self.routines.is_empty())
+            .filter(|instruction| depth < 2 || !instruction.forms.iter().any(|form| form.operands.contains(&OperandType::Block)))
+            .collect();
+
+        let instruction = *self.rng.pick(&instructions);
+        let form = self.rng.pick(instruction.forms);
+
+        let mut text = vec![instruction.mnemonic.to_string()];
+
+        match (instruction.mnemonic, form.operands) {
+            ("var" | "let", _) => {
+                // Leave room for a string of up to 3 variables.
+                text.push(self.var(0xfd));
+                text.push(self.value());
+            }
+            ("rep", _) => {
+                let count = self.rng.range(0, 3).to_string();
+                let constant = self.constant(|value| !value.starts_with(['+', '#']));
+                text.push(constant.filter(|_| self.rng.chance(2)).unwrap_or(count));
+                text.push(self.block(depth + 1));
+            }
+            (_, operands) => {
+                for &ty in operands {
+                    text.push(self.operand(ty, depth));
+                }
+            }
+        }
+
+        text.join(" ")
+    }
+
+    fn operand(&mut self, ty: OperandType, depth: usize) -> String {
+        let constant = match ty {
+            OperandType::Byte => self.constant(|value| !value.starts_with('#')),
+            OperandType::Float => self.constant(|value| value.starts_with('#')),
+            _ => None,
+        };
+
+        if let Some(constant) = constant && self.rng.chance(3) {
+            return constant
+        }
+
+        match ty {
+            OperandType::Var | OperandType::Out => self.var(0xff),
+            OperandType::Byte if self.rng.chance(2) => self.rng.below(256).to_string(),
+            OperandType::Byte | OperandType::Bytes => format!("+{:02x}", self.rng.below(256)),
+            // Never zero, so folded divisions do not fail.
+            OperandType::Num => [self.rng.range(1, 1000), self.rng.range(-1000, -1)][self.rng.below(2)].to_string(),
+            OperandType::Float => self.fold_float(),
+            OperandType::Value => self.value(),
+            OperandType::Label => self.rng.pick(&LABELS).to_string(),
+            OperandType::Name => self.rng.pick(&self.routines).clone(),
+            OperandType::Block => self.block(depth + 1),
+            OperandType::Array => {
+                let elements: Vec<String> = (0..8).map(|_| self.var(0xff)).collect();
+                format!("[{}]", elements.join(" "))
+            }
+        }
+    }
+
+    fn float(&mut self) -> String {
+        loop {
+            let float = f64::from_bits(self.rng.next());
+
+            if float.is_finite() {
+                return format!("#{float:?}")
+            }
+        }
+    }
+
+    /// A float that folds with any other without overflowing or dividing by zero.
+    fn fold_float(&mut self) -> String {
+        loop {
+            let float = f64::from_bits(self.rng.next());
+
+            if (1e-100..1e100).contains(&float.abs()) {
+                return format!("#{float:?}")
+            }
+        }
+    }
+
+    fn value(&mut self) -> String {
+        match self.rng.below(4) {
+            0 => self.rng.range(i64::MIN / 2, i64::MAX / 2).to_string(),
+            1 => self.float(),
+            2 => {
+                let elements: Vec<String> = (0..8).map(|_| format!("+{:02x}", self.rng.below(256))).collect();
+                format!("[{}]", elements.join(" "))
+            }
+            _ => {
+                let chars: Vec<String> = (0..self.rng.below(24))
+                    .map(|_| match self.rng.below(12) {
+                        0 => self.rng.pick(&["\\n", "\\t", "\\0", "\\\\", "\\\"", "\\'"]).to_string(),
+                        1 => format!("\\x{:02x}", self.rng.below(256)),
+                        _ => {
+                            let chr = self.rng.range(0x20, 0x7e) as u8 as char;
+                            if matches!(chr, '"' | '\\') { format!("\\{chr}") } else { chr.to_string() }
+                        }
+                    })
+                    .collect();
+                format!("\"{}\"", chars.concat())
+            }
+        }
+    }
+}
+
+/// Assembles `source`, panicking with the diagnostics and `context` if it fails.
+fn assemble_ok(source: &str, context: &str) -> Vec<u8> {
+    let mut assembler = Assembler::new();
+
+    assembler.assemble(source).unwrap_or_else(|diagnostics| {
+        panic!("{context}:\n{source}\n\n{}", report(&diagnostics, assembler.sources()))
+    })
+}
+
+#[test]
+fn random_programs_round_trip() {
+    for seed in 0..CASES {
+        let source = Generator { rng: Rng::new(seed), routines: vec![], names: vec![], constants: vec![] }.program();
+
+        let bytes = assemble_ok(&source, &format!("seed {seed}: generated program failed to assemble"));
+        let disassembly = disassemble(&bytes);
+        let reassembled = assemble_ok(&disassembly, &format!("seed {seed}: disassembly of\n{source}\nfailed to assemble"));
+
+        assert_eq!(bytes, reassembled, "seed {seed}: reassembled bytes differ\n{source}\n\n{disassembly}");
+        assert_eq!(disassemble(&reassembled), disassembly, "seed {seed}: disassembly is not stable");
+    }
+}
+
+#[test]
+fn main_asc_round_trips() {
+    let bytes = assemble(include_str!("../main.asc")).unwrap();
+
+    assert_eq!(assemble(&disassemble(&bytes)).unwrap(), bytes);
+}
@@ -0,0 +1,121 @@
+//! The tokens source text turns into, and the errors malformed text reports.
+
+use asc_script::tokenizer::tokenize;
+use asc_script::tokens::TokenKind::{self, *};
+use asc_script::{Diagnostics, FileId};
+
+/// The kinds of the tokens of `source`, panicking if it fails to tokenize.
+fn kinds(source: &str) -> Vec<TokenKind> {
+    let mut diagnostics = Diagnostics::new();
+    let tokens = tokenize(source, FileId(0), &mut diagnostics);
+
+    let messages: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.message()).collect();
+    assert!(diagnostics.is_empty(), "`{source}` should tokenize, but got {messages:?}");
+
+    tokens.into_iter().map(|token| token.kind).collect()
+}
+
+/// The codes of the errors tokenizing `source` reports.
+fn errors(source: &str) -> Vec<&'static str> {
+    let mut diagnostics = Diagnostics::new();
+    tokenize(source, FileId(0), &mut diagnostics);
+
+    diagnostics.iter().map(|diagnostic| diagnostic.code()).collect()
+}
+
+fn ident(name: &str) -> TokenKind {
+    Ident(name.to_string())
+}
+
+fn label(name: &str) -> TokenKind {
+    Label(name.to_string())
+}
+
+#[test]
+fn identifiers_and_labels() {
+    // An identifier ends at the first character that cannot continue it.
+    assert_eq!(kinds("noop;flsh"), [ident("noop"), EndL, ident("flsh")]);
+    assert_eq!(kinds("game_loop2 _x Up"), [ident("game_loop2"), ident("_x"), ident("Up")]);
+    assert_eq!(kinds("!loop2 !game_loop !_"), [label("loop2"), label("game_loop"), label("_")]);
+    assert_eq!(kinds("!top;jmp top"), [label("top"), EndL, ident("jmp"), ident("top")]);
+    assert_eq!(kinds("{ } [ ]"), [OpenBrace, CloseBrace, OpenBracket, CloseBracket]);
+
+    assert_eq!(errors("!"), ["E0002"]);
+    assert_eq!(errors("!2x"), ["E0002", "E0002"]);
+    assert_eq!(errors("%"), ["E0001"]);
+}
+
+#[test]
+fn comments() {
+    assert_eq!(kinds("flsh // cls +01\nnoop"), [ident("flsh"), ident("noop")]);
+    assert_eq!(kinds("flsh /* cls\n+01 */ noop"), [ident("flsh"), ident("noop")]);
+    assert_eq!(kinds("flsh / cls +01 / noop"), [ident("flsh"), ident("noop")]);
+    assert_eq!(kinds("flsh // no newline"), [ident("flsh")]);
+
+    // Block comments nest, and hide the other forms.
+    assert_eq!(kinds("/* a /* b */ c */ flsh"), [ident("flsh")]);
+    assert_eq!(kinds("/* / // */ flsh"), [ident("flsh")]);
+    assert_eq!(kinds("// /* \nflsh"), [ident("flsh")]);
+
+    assert_eq!(errors("flsh /* a /* b */"), ["E0008"]);
+    assert_eq!(errors("flsh / never closed"), ["E0008"]);
+
+    let mut diagnostics = Diagnostics::new();
+    tokenize("flsh\n\nnoop /* never\nclosed", FileId(0), &mut diagnostics);
+    assert_eq!(diagnostics.iter().next().unwrap().message(), "Unterminated comment starting at line 3");
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(kinds("42 -7 1_000"), [Num(42), Num(-7), Num(1000)]);
+    assert_eq!(kinds("0x1f 0b101 0o17 -0x10"), [Num(0x1f), Num(0b101), Num(0o17), Num(-0x10)]);
+    assert_eq!(kinds("@ff @1_00"), [Num(0xff), Num(0x100)]);
+    assert_eq!(kinds("9223372036854775807 -9223372036854775808"), [Num(i64::MAX), Num(i64::MIN)]);
+    assert_eq!(kinds("'a' ' ' '\\n' '\\'' '\\x41'"), [Num(97), Num(32), Num(10), Num(39), Num(0x41)]);
+
+    assert_eq!(errors("9223372036854775808"), ["E0002"]);
+    assert_eq!(errors("1_"), ["E0002"]);
+    assert_eq!(errors("0x"), ["E0002"]);
+    assert_eq!(errors("0b102"), ["E0002"]);
+    assert_eq!(errors("-"), ["E0002"]);
+    assert_eq!(errors("'ab'"), ["E0002"]);
+    assert_eq!(errors("''"), ["E0002"]);
+}
+
+#[test]
+fn floats() {
+    assert_eq!(kinds("#1.5 #-0.25 #2 #6.02e23"), [Float(1.5), Float(-0.25), Float(2.0), Float(6.02e23)]);
+    assert_eq!(kinds("1.5 1e3 1_0.5"), [Float(1.5), Float(1000.0), Float(10.5)]);
+
+    assert_eq!(errors("#inf"), ["E0002"]);
+    assert_eq!(errors("#1e400"), ["E0002"]);
+    assert_eq!(errors("-1e400"), ["E0002"]);
+    assert_eq!(errors("#1.2.3"), ["E0002"]);
+}
+
+#[test]
+fn bytes_and_variables() {
+    assert_eq!(kinds("+00 +ff +Ab"), [Byte(0x00), Byte(0xff), Byte(0xab)]);
+    assert_eq!(kinds("$00 $ff $0a"), [Var(0x00), Var(0xff), Var(0x0a)]);
+
+    // `@` followed by anything but a hex digit pins a name instead of starting a number.
+    assert_eq!(kinds("var up @ $d1 = 3"), [ident("var"), ident("up"), At, Var(0xd1), Assign, Num(3)]);
+    assert_eq!(kinds("up@$d1 up=@d1"), [ident("up"), At, Var(0xd1), ident("up"), Assign, Num(0xd1)]);
+
+    assert_eq!(errors("+g0"), ["E0002"]);
+    assert_eq!(errors("+1"), ["E0005"]);
+    assert_eq!(errors("$100"), ["E0003"]);
+    assert_eq!(errors("$"), ["E0002"]);
+}
+
+#[test]
+fn strings() {
+    assert_eq!(kinds("\"say \\\"hi\\\"\\t\\0\""), [Str(b"say \"hi\"\t\0".to_vec())]);
+    assert_eq!(kinds("\"a\\\\b\\x41\\n\""), [Str(b"a\\bA\n".to_vec())]);
+    assert_eq!(kinds("\"\""), [Str(vec![])]);
+
+    assert_eq!(errors("'\\q'"), ["E0010"]);
+    assert_eq!(errors("\"\\q\""), ["E0010"]);
+    assert_eq!(errors("\"é\""), ["E0009"]);
+    assert_eq!(errors("\"open"), ["E0005"]);
+}